@@ -1,8 +1,9 @@
+use crate::common;
 use crate::common::ServiceRef;
 use crate::fetch_service::{RunningTask, Task, TaskItem};
 use crate::model_service;
 use crate::model_service::ModelServiceArgs;
-use crate::{config, fetch_service, file_service};
+use crate::{config, fetch_service, file_service, model_log_service, process_service};
 use actix_cors::Cors;
 use actix_web::middleware::Logger;
 use actix_web::{App, HttpResponse, HttpServer, Responder, web};
@@ -47,6 +48,17 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .service(crate::model_api::start_model_server)
             .service(crate::model_api::get_model_servers)
             .service(crate::model_api::stop_model_server)
+            .service(crate::model_api::inspect_model)
+            .service(crate::model_api::list_model_profiles)
+            .service(crate::model_api::save_model_profile)
+            .service(crate::model_api::delete_model_profile)
+            .service(crate::model_api::set_default_model_profile)
+            .service(crate::model_api::list_chat_templates)
+            .service(crate::model_api::upload_chat_template)
+            .service(crate::model_api::delete_chat_template)
+            .service(crate::model_api::set_model_chat_template)
+            .service(crate::model_api::get_model_logs)
+            .service(crate::model_api::get_model_logs_stream)
             .service(get_status)
             .service(crate::fetch_api::start_fetch)
             .service(crate::fetch_api::get_fetches)
@@ -56,11 +68,28 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .service(crate::fetch_api::stop_fetch)
             .service(crate::fetch_api::resume_fetch)
             .service(crate::fetch_api::update_fetch)
+            .service(crate::fetch_api::reorder_fetch)
+            .service(crate::fetch_api::promote_fetch)
+            .service(crate::fetch_api::get_fetch_bandwidth)
+            .service(crate::fetch_api::update_fetch_bandwidth)
             .service(crate::process_api::heart_tick)
             .service(crate::system_api::notify)
             .service(crate::worker_api::start_worker)
             .service(crate::worker_api::heart_tick),
     );
+    cfg.service(
+        web::scope("/v1")
+            // Default payload limit is too small for audio files and images
+            .app_data(web::PayloadConfig::new(common::GATEWAY_MAX_PAYLOAD_SIZE))
+            .service(crate::gateway_api::chat_completions)
+            .service(crate::gateway_api::completions)
+            .service(crate::gateway_api::embeddings)
+            .service(crate::gateway_api::rerank)
+            .service(crate::gateway_api::images_generations)
+            .service(crate::gateway_api::images_edits)
+            .service(crate::gateway_api::audio_transcriptions)
+            .service(crate::gateway_api::get_models),
+    );
 }
 
 /// Start Web Server
@@ -74,6 +103,11 @@ pub async fn start_server() -> std::io::Result<()> {
     file_service::init_file_service();
     // Initialize fetch service
     fetch_service::initialize();
+    // Logs of previous runs are pruned before model servers are started again
+    model_log_service::prune_model_logs();
+    // Restore model servers which were running before restart. It can't be done in
+    // synvek::initialize since model server sub processes are initialized there too.
+    model_service::restore_model_servers().await;
 
     // Start web server
     HttpServer::new(|| {
@@ -90,5 +124,9 @@ pub async fn start_server() -> std::io::Result<()> {
     })
     .bind((host, port))?
     .run()
-    .await
+    .await?;
+
+    // Model processes are stopped gracefully on shutdown
+    let _ = tokio::task::spawn_blocking(process_service::stop_all_processes).await;
+    Ok(())
 }
@@ -0,0 +1,534 @@
+use crate::fetch_service::Task;
+use crate::model_service::ModelServiceArgs;
+use crate::{common, mock_server, model_service, utils};
+use async_trait::async_trait;
+use libloading::{Library, Symbol};
+use std::collections::HashMap;
+use std::ffi::{CString, OsString, c_char, c_int};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+type StartLibraryServer = unsafe fn(i32, *const *const c_char) -> i32;
+type InitLogCallback = unsafe fn(Option<extern "C" fn(i32, *const c_char)>) -> ();
+type CleanupLogCallback = unsafe fn() -> ();
+
+/// Everything backend needs to build arguments and start model server
+pub struct BackendContext<'a> {
+    pub args: &'a ModelServiceArgs,
+
+    pub task: &'a Task,
+
+    pub model_dir: PathBuf,
+
+    pub log_dir: PathBuf,
+
+    pub task_id: &'a str,
+
+    pub port: &'a str,
+
+    pub path: &'a str,
+
+    pub is_spawn_process: bool,
+}
+
+/// Inference backend which runs model server, backend is selected by name in ModelServiceArgs.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Name used in ModelServiceArgs.backend
+    fn name(&self) -> &'static str;
+
+    /// Base name of backend library, acceleration suffix is added when it is loaded
+    fn library_name(&self) -> &'static str;
+
+    /// Accelerations supported on current platform
+    fn supported_accelerations(&self) -> Vec<&'static str>;
+
+    /// Path polled until model server is ready, none if backend notifies main process by itself
+    fn health_path(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Whether downloaded model is required, backend without model can start with any model name
+    fn requires_model(&self) -> bool {
+        true
+    }
+
+    /// Capability of model server for model type, it tells which OpenAI compatible endpoint model
+    /// server serves
+    fn capability(&self, model_type: &str) -> &'static str {
+        get_default_capability(model_type)
+    }
+
+    /// Model types which are served only by backends listing them, e.g. embedding and rerank
+    fn supported_model_types(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// Whether LoRA adapters can be applied on model server
+    fn supports_lora(&self) -> bool {
+        false
+    }
+
+    /// Whether model server running in this process stops when its start future is dropped, backend
+    /// blocking in library call keeps running until it returns by itself
+    fn can_stop_in_process(&self) -> bool {
+        true
+    }
+
+    /// Append backend arguments for private or public model of task
+    fn populate_args(&self, context: &BackendContext<'_>, start_args: &mut Vec<OsString>);
+
+    /// Run model server, it returns after model server exits
+    async fn start(&self, context: &BackendContext<'_>, start_args: &Vec<OsString>);
+}
+
+static BACKENDS: OnceLock<Arc<Mutex<HashMap<String, Arc<dyn Backend>>>>> = OnceLock::new();
+
+fn init_backends() -> Arc<Mutex<HashMap<String, Arc<dyn Backend>>>> {
+    let mut backends: HashMap<String, Arc<dyn Backend>> = HashMap::new();
+    let builtin_backends: Vec<Arc<dyn Backend>> = vec![
+        Arc::new(DefaultBackend),
+        Arc::new(LlamaCppBackend),
+        Arc::new(StableDiffusionCppBackend),
+        Arc::new(WhisperCppBackend),
+        Arc::new(MockBackend),
+    ];
+    builtin_backends.into_iter().for_each(|backend| {
+        backends.insert(backend.name().to_string(), backend);
+    });
+    Arc::new(Mutex::new(backends))
+}
+
+pub fn register_backend(backend: Arc<dyn Backend>) {
+    let backends = BACKENDS.get_or_init(|| init_backends());
+    let mut backends = backends.lock().unwrap();
+    backends.insert(backend.name().to_string(), backend);
+}
+
+pub fn get_backend(name: &str) -> Option<Arc<dyn Backend>> {
+    let backends = BACKENDS.get_or_init(|| init_backends());
+    let backends = backends.lock().unwrap();
+    backends.get(name).cloned()
+}
+
+/// Capability of model type on backend without dedicated capability
+fn get_default_capability(model_type: &str) -> &'static str {
+    if model_type == "diffusion" {
+        common::CAPABILITY_IMAGE
+    } else if model_type == "speech" {
+        common::CAPABILITY_SPEECH
+    } else if model_type == common::MODEL_TYPE_EMBEDDING {
+        common::CAPABILITY_EMBEDDING
+    } else if model_type == common::MODEL_TYPE_RERANK {
+        common::CAPABILITY_RERANK
+    } else {
+        common::CAPABILITY_CHAT
+    }
+}
+
+/// Whether backend serves model type, model types outside of backend model types are served by
+/// every backend
+pub fn validate_model_type(backend: &str, model_type: &str) -> bool {
+    if !common::BACKEND_MODEL_TYPES.contains(&model_type) {
+        return true;
+    }
+    get_backend(backend)
+        .map(|backend| backend.supported_model_types().contains(&model_type))
+        .unwrap_or(false)
+}
+
+pub fn validate_acceleration(backend: &str, acceleration: &str) -> bool {
+    get_backend(backend)
+        .map(|backend| backend.supported_accelerations().contains(&acceleration))
+        .unwrap_or(false)
+}
+
+/// Accelerations of backends built on ggml
+fn get_ggml_accelerations() -> Vec<&'static str> {
+    if cfg!(target_os = "windows") {
+        vec![
+            common::ACCELERATION_CPU,
+            common::ACCELERATION_CUDA,
+            common::ACCELERATION_VULKAN,
+            common::ACCELERATION_HIP,
+        ]
+    } else if cfg!(target_os = "macos") {
+        vec![common::ACCELERATION_CPU, common::ACCELERATION_METAL]
+    } else {
+        vec![
+            common::ACCELERATION_CPU,
+            common::ACCELERATION_CUDA,
+            common::ACCELERATION_VULKAN,
+        ]
+    }
+}
+
+/// Backend of mistral.rs
+pub struct DefaultBackend;
+
+#[async_trait]
+impl Backend for DefaultBackend {
+    fn name(&self) -> &'static str {
+        common::BACKEND_DEFAULT
+    }
+
+    fn library_name(&self) -> &'static str {
+        "synvek_backend_default"
+    }
+
+    fn can_stop_in_process(&self) -> bool {
+        false
+    }
+
+    fn supported_accelerations(&self) -> Vec<&'static str> {
+        if cfg!(target_os = "macos") {
+            vec![common::ACCELERATION_CPU, common::ACCELERATION_METAL]
+        } else {
+            vec![
+                common::ACCELERATION_CPU,
+                common::ACCELERATION_CUDA,
+                common::ACCELERATION_CUDA_LEGACY,
+            ]
+        }
+    }
+
+    fn populate_args(&self, context: &BackendContext<'_>, start_args: &mut Vec<OsString>) {
+        if context.task.private_model {
+            model_service::populate_args_private_with_backend_default(
+                context.task,
+                context.model_dir.clone(),
+                context.log_dir.clone(),
+                start_args,
+            );
+        } else {
+            model_service::populate_args_with_backend_default(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                context.log_dir.clone(),
+                start_args,
+            );
+        }
+    }
+
+    async fn start(&self, context: &BackendContext<'_>, start_args: &Vec<OsString>) {
+        model_service::start_mistral_server_dll(
+            context.args,
+            start_args,
+            context.task_id,
+            context.port,
+            context.path,
+            context.is_spawn_process,
+        )
+        .await;
+    }
+}
+
+/// Backend of llama.cpp server
+pub struct LlamaCppBackend;
+
+#[async_trait]
+impl Backend for LlamaCppBackend {
+    fn name(&self) -> &'static str {
+        common::BACKEND_LLAMA_CPP
+    }
+
+    fn library_name(&self) -> &'static str {
+        "synvek_backend_llama"
+    }
+
+    fn supported_accelerations(&self) -> Vec<&'static str> {
+        get_ggml_accelerations()
+    }
+
+    fn health_path(&self) -> Option<&'static str> {
+        Some("/health")
+    }
+
+    fn supported_model_types(&self) -> Vec<&'static str> {
+        vec![common::MODEL_TYPE_EMBEDDING, common::MODEL_TYPE_RERANK]
+    }
+
+    fn supports_lora(&self) -> bool {
+        true
+    }
+
+    fn can_stop_in_process(&self) -> bool {
+        false
+    }
+
+    fn populate_args(&self, context: &BackendContext<'_>, start_args: &mut Vec<OsString>) {
+        if context.task.private_model {
+            model_service::populate_args_private_with_backend_llama_cpp(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        } else {
+            model_service::populate_args_with_backend_llama_cpp(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        }
+    }
+
+    async fn start(&self, context: &BackendContext<'_>, start_args: &Vec<OsString>) {
+        start_library_server(
+            self,
+            context,
+            start_args,
+            b"start_llama_server",
+            model_service::handle_llama_cpp_log_callback,
+        );
+    }
+}
+
+/// Backend of stable-diffusion.cpp, it is served by sd server in this process
+pub struct StableDiffusionCppBackend;
+
+#[async_trait]
+impl Backend for StableDiffusionCppBackend {
+    fn name(&self) -> &'static str {
+        common::BACKEND_STABLE_DIFFUSION_CPP
+    }
+
+    fn library_name(&self) -> &'static str {
+        "synvek_backend_sd"
+    }
+
+    fn supported_accelerations(&self) -> Vec<&'static str> {
+        get_ggml_accelerations()
+    }
+
+    fn capability(&self, _model_type: &str) -> &'static str {
+        common::CAPABILITY_IMAGE
+    }
+
+    fn populate_args(&self, context: &BackendContext<'_>, start_args: &mut Vec<OsString>) {
+        if context.task.private_model {
+            model_service::populate_args_private_with_backend_stable_diffusion_cpp(
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        } else {
+            model_service::populate_args_with_backend_stable_diffusion_cpp(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        }
+    }
+
+    async fn start(&self, context: &BackendContext<'_>, start_args: &Vec<OsString>) {
+        model_service::start_stable_diffusion_cpp_server(
+            context.args,
+            start_args,
+            context.task_id,
+            context.port,
+            context.path,
+            context.is_spawn_process,
+        )
+        .await;
+    }
+}
+
+/// Backend of whisper.cpp server
+pub struct WhisperCppBackend;
+
+#[async_trait]
+impl Backend for WhisperCppBackend {
+    fn name(&self) -> &'static str {
+        common::BACKEND_WHISPER_CPP
+    }
+
+    fn library_name(&self) -> &'static str {
+        "synvek_backend_whisper"
+    }
+
+    fn supported_accelerations(&self) -> Vec<&'static str> {
+        get_ggml_accelerations()
+    }
+
+    fn health_path(&self) -> Option<&'static str> {
+        Some("/health")
+    }
+
+    fn capability(&self, _model_type: &str) -> &'static str {
+        common::CAPABILITY_TRANSCRIPTION
+    }
+
+    fn can_stop_in_process(&self) -> bool {
+        false
+    }
+
+    fn populate_args(&self, context: &BackendContext<'_>, start_args: &mut Vec<OsString>) {
+        if context.task.private_model {
+            model_service::populate_args_private_with_backend_whisper_cpp(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        } else {
+            model_service::populate_args_with_backend_whisper_cpp(
+                context.args,
+                context.task,
+                context.model_dir.clone(),
+                start_args,
+            );
+        }
+    }
+
+    async fn start(&self, context: &BackendContext<'_>, start_args: &Vec<OsString>) {
+        start_library_server(
+            self,
+            context,
+            start_args,
+            b"start_whisper_server",
+            model_service::handle_whisper_cpp_log_callback,
+        );
+    }
+}
+
+/// Backend served from Rust with canned responses, it is used to test model server flows without
+/// native backend library.
+pub struct MockBackend;
+
+#[async_trait]
+impl Backend for MockBackend {
+    fn name(&self) -> &'static str {
+        common::BACKEND_MOCK
+    }
+
+    fn library_name(&self) -> &'static str {
+        ""
+    }
+
+    fn supported_accelerations(&self) -> Vec<&'static str> {
+        vec![
+            common::ACCELERATION_CPU,
+            common::ACCELERATION_CUDA,
+            common::ACCELERATION_CUDA_LEGACY,
+            common::ACCELERATION_VULKAN,
+            common::ACCELERATION_HIP,
+            common::ACCELERATION_METAL,
+        ]
+    }
+
+    fn health_path(&self) -> Option<&'static str> {
+        Some("/health")
+    }
+
+    fn requires_model(&self) -> bool {
+        false
+    }
+
+    fn populate_args(&self, _context: &BackendContext<'_>, _start_args: &mut Vec<OsString>) {}
+
+    async fn start(&self, context: &BackendContext<'_>, _start_args: &Vec<OsString>) {
+        model_service::start_server_monitor(
+            context.task_id.to_string(),
+            context.port.to_string(),
+            "/health".to_string(),
+        );
+        let result = mock_server::start_mock_server(context.port).await;
+        tracing::info!("Mock server exited with result: {:?}", result);
+        if result.is_err() && context.is_spawn_process {
+            panic!(
+                "Failed to run mock server, args = {:?} ",
+                context.args.clone(),
+            );
+        }
+    }
+}
+
+/// Load backend library which exports main like start function with log callbacks, and run it
+/// until model server exits.
+fn start_library_server(
+    backend: &dyn Backend,
+    context: &BackendContext<'_>,
+    start_args: &Vec<OsString>,
+    start_symbol: &[u8],
+    log_callback: extern "C" fn(i32, *const c_char),
+) {
+    let acceleration = context.args.acceleration.clone();
+    let lib_name = utils::get_load_library_name(backend.library_name(), acceleration.as_str());
+    let lib_name = utils::get_backend_path(lib_name.as_str());
+    let backend_name = backend.name();
+
+    unsafe {
+        tracing::info!("Search {} server with name: {}", backend_name, lib_name.clone());
+        let lib = Library::new(lib_name);
+        if let Ok(lib) = lib {
+            tracing::info!("Loading {} server...", backend_name);
+            let start_server_func = lib.get(start_symbol);
+            let init_log_callback_func = lib.get(b"init_log_callback");
+            let cleanup_log_callback_func = lib.get(b"cleanup_log_callback");
+            match (
+                start_server_func,
+                init_log_callback_func,
+                cleanup_log_callback_func,
+            ) {
+                (
+                    Ok(start_server_func),
+                    Ok(init_log_callback_func),
+                    Ok(cleanup_log_callback_func),
+                ) => {
+                    let start_server: Symbol<StartLibraryServer> = start_server_func;
+                    let init_log_callback: Symbol<InitLogCallback> = init_log_callback_func;
+                    let cleanup_log_callback: Symbol<CleanupLogCallback> = cleanup_log_callback_func;
+                    let c_strings = start_args
+                        .iter()
+                        .map(|s| CString::new(s.to_str().unwrap()))
+                        .collect::<anyhow::Result<Vec<_>, _>>();
+                    if let Ok(c_strings) = c_strings {
+                        let raw_ptrs: Vec<*const c_char> =
+                            c_strings.iter().map(|cs| cs.as_ptr()).collect();
+                        tracing::info!("Starting {} server...", backend_name);
+                        if let Some(health_path) = backend.health_path() {
+                            model_service::start_server_monitor(
+                                context.task_id.to_string(),
+                                context.port.to_string(),
+                                health_path.to_string(),
+                            );
+                        }
+                        init_log_callback(Some(log_callback));
+                        let result = start_server(raw_ptrs.len() as c_int, raw_ptrs.as_ptr());
+                        cleanup_log_callback();
+                        tracing::info!("{} server exited with result: {}", backend_name, result);
+                    }
+                }
+                _ => {
+                    tracing::error!("Failed to load functions of backend {} server.", backend_name);
+                    // Need to terminate in multiprocess mode  right now.
+                    if context.is_spawn_process {
+                        panic!(
+                            "Failed to load functions of backend {} server, args = {:?} ",
+                            backend_name,
+                            context.args.clone(),
+                        );
+                    }
+                }
+            }
+        } else {
+            tracing::error!(
+                "Failed to load backend {} server with error {:}.",
+                backend_name,
+                lib.unwrap_err()
+            );
+            // Need to terminate in multiprocess mode  right now.
+            if context.is_spawn_process {
+                panic!(
+                    "Failed to load backend {} server, args = {:?} ",
+                    backend_name,
+                    context.args.clone(),
+                );
+            }
+        }
+    }
+}
@@ -7,7 +7,7 @@ use anyhow::{Error, Result, anyhow};
 use tokio::time::sleep;
 use crate::{fetch_service, file_service, sd_service};
 use crate::worker_service::WorkerArgs;
-use crate::model_service::ModelServiceArgs;
+use crate::model_service::{LoraAdapter, ModelServiceArgs};
 use crate::worker_service::WorkerType;
 use libloading::{Library, Symbol};
 use crate::sd_service::GenerationArgs;
@@ -144,6 +144,26 @@ pub struct StartArgs {
     #[arg(long)]
     pub rope_freq_base: Option<u32>,
 
+    /// Requests served at same time
+    #[arg(long)]
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting in queue
+    #[arg(long)]
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue
+    #[arg(long)]
+    pub queue_timeout: Option<u64>,
+
+    /// LoRA adapter as name or name:scale, it can be repeated
+    #[arg(long = "lora-adapter")]
+    pub lora_adapters: Option<Vec<LoraAdapter>>,
+
+    /// Pooling of embedding or rerank model
+    #[arg(long)]
+    pub pooling: Option<String>,
+
 }
 
 /// Service Args
@@ -266,6 +286,22 @@ impl CommandHandler {
             rope_scaling: args.rope_scaling,
             rope_scale: args.rope_scale,
             rope_freq_base: args.rope_freq_base,
+            autostart: false,
+            restart_policy: None,
+            restart_max_retries: None,
+            idle_timeout: None,
+            start_on_demand: false,
+            memory_limit: None,
+            cpu_affinity: None,
+            nice: None,
+            replica_group: None,
+            load_balancing: None,
+            max_concurrency: args.max_concurrency,
+            max_queue_size: args.max_queue_size,
+            queue_timeout: args.queue_timeout,
+            lora_adapters: args.lora_adapters,
+            pooling: args.pooling,
+            ignore_memory_check: false,
         };
         crate::model_service::start_model_server_from_command(&model_args, args.task_id.as_str(), args.port.as_str()).await?;
         loop {
@@ -22,8 +22,23 @@ pub static EMBEDDING_DIR_NAME: &str = "embedding";
 
 pub static UPSCALE_DIR_NAME: &str = "upscale";
 
+pub static CHAT_TEMPLATE_DIR_NAME: &str = "chat_templates";
+
 pub static LOG_DIR_NAME: &str = "logs";
 
+pub static MODEL_LOG_DIR_NAME: &str = "models";
+
+pub static MODEL_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+pub static MODEL_LOG_MAX_FILES: u32 = 3;
+
+/// Count of task logs kept from previous service runs, older ones are removed at service start
+pub static MODEL_LOG_MAX_TASKS: usize = 32;
+
+pub static MODEL_LOG_TAIL_SIZE: u64 = 1024 * 1024;
+
+pub static MODEL_LOG_DEFAULT_LINES: usize = 200;
+
 pub static BACKEND_DIR_NAME: &str = "backend";
 
 pub static CONFIG_FILE: &str = "config.json";
@@ -37,6 +52,15 @@ pub static TASKS_FILE: &str = "tasks.json";
 
 pub static WORKERS_FILE: &str = "workers.json";
 
+pub static MODEL_SERVERS_FILE: &str = "model_servers.json";
+
+pub static PROFILES_FILE: &str = "profiles.json";
+
+pub static CHAT_TEMPLATES_FILE: &str = "chat_templates.json";
+
+/// Largest chat template accepted on upload, in bytes
+pub static MAX_CHAT_TEMPLATE_SIZE: usize = 256 * 1024;
+
 pub static CONFIG_CACHE_PATH: &str = "cache_path";
 pub static CONFIG_ENDPOINT: &str = "endpoint";
 
@@ -46,10 +70,18 @@ pub static CONFIG_AGENT_PORT: &str = "agent_port";
 
 pub static CONFIG_MODEL_PORT: &str = "model_port";
 
+pub static CONFIG_MODEL_PORT_RANGE: &str = "model_port_range";
+
+pub static CONFIG_MODEL_STOP_TIMEOUT: &str = "model_stop_timeout";
+
 pub static CONFIG_MULTI_PROCESS: &str = "multi_process";
 
 pub static CONFIG_ENABLE_DEBUG_LOG: &str = "enable_debug_log";
 
+pub static CONFIG_MAX_CONCURRENT_DOWNLOADS: &str = "max_concurrent_downloads";
+
+pub static CONFIG_DOWNLOAD_BANDWIDTH_LIMIT: &str = "download_bandwidth_limit";
+
 pub static MODELS_DIR: &str = "models_dir";
 
 pub static LORA_DIR: &str = "lora_dir";
@@ -66,6 +98,25 @@ pub static CACHE_REPO_FILES_SLEEP_DURATION: u64 = 7200u64;
 
 pub static DOWNLOAD_RETRY_COUNT_LIMIT: u64 = 5;
 
+/// Files of one task downloaded at same time
+pub static DEFAULT_DOWNLOAD_CONCURRENCY: u32 = 2;
+
+pub static MAX_DOWNLOAD_CONCURRENCY: u32 = 8;
+
+/// Priority of download task if it is not provided
+pub static DEFAULT_DOWNLOAD_PRIORITY: i32 = 0;
+
+/// Files from this size are downloaded by parallel range requests
+pub static RANGE_DOWNLOAD_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+pub static RANGE_DOWNLOAD_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Range requests of one file at same time
+pub static RANGE_DOWNLOAD_CONNECTIONS: usize = 4;
+
+/// Retries of one chunk before download of file fails
+pub static RANGE_DOWNLOAD_RETRY_COUNT: u32 = 3;
+
 pub static BACKEND_DEFAULT: &str = "default";
 
 pub static BACKEND_LLAMA_CPP: &str = "llama_cpp";
@@ -74,8 +125,31 @@ pub static BACKEND_STABLE_DIFFUSION_CPP: &str = "stable_diffusion_cpp";
 
 pub static BACKEND_WHISPER_CPP: &str = "whisper_cpp";
 
+pub static BACKEND_MOCK: &str = "mock";
+
 pub static BACKEND_UNKNOWN: &str = "unknown";
 
+pub static MODEL_TYPE_EMBEDDING: &str = "embedding";
+
+pub static MODEL_TYPE_RERANK: &str = "rerank";
+
+/// Model types served only by backends which support them
+pub static BACKEND_MODEL_TYPES: [&str; 2] = [MODEL_TYPE_EMBEDDING, MODEL_TYPE_RERANK];
+
+pub static POOLING_TYPES: [&str; 5] = ["none", "mean", "cls", "last", "rank"];
+
+pub static CAPABILITY_CHAT: &str = "chat";
+
+pub static CAPABILITY_EMBEDDING: &str = "embedding";
+
+pub static CAPABILITY_RERANK: &str = "rerank";
+
+pub static CAPABILITY_IMAGE: &str = "image";
+
+pub static CAPABILITY_SPEECH: &str = "speech";
+
+pub static CAPABILITY_TRANSCRIPTION: &str = "transcription";
+
 pub static ACCELERATION_CPU: &str = "cpu";
 
 pub static ACCELERATION_CPU_MKL: &str = "cpu-mkl";
@@ -100,6 +174,69 @@ pub static ACCELERATION_UNKNOWN: &str = "unknown";
 
 pub static HEALTH_CHECK_COUNT: i32 = 1200;
 
+/// Interval of health probe after model server is ready
+pub static HEALTH_PROBE_INTERVAL_SECS: u64 = 10;
+
+pub static PROCESS_STATE_HISTORY_SIZE: usize = 32;
+
+/// Count of stopped or failed processes kept for querying their final state
+pub static FINISHED_PROCESS_HISTORY_SIZE: usize = 32;
+
+pub static MOCK_CHAT_CONTENT: &str = "This is a response from mock backend.";
+
+pub static MOCK_IMAGE_SIZE: usize = 64;
+
+pub static RESTART_POLICY_NEVER: &str = "never";
+
+pub static RESTART_POLICY_ON_FAILURE: &str = "on-failure";
+
+pub static RESTART_POLICY_ALWAYS: &str = "always";
+
+pub static RESTART_MAX_RETRIES: u32 = 5;
+
+pub static RESTART_BACKOFF_BASE_SECS: u64 = 2;
+
+pub static RESTART_BACKOFF_MAX_SECS: u64 = 300;
+
+pub static IDLE_CHECK_INTERVAL_SECS: u64 = 10;
+
+pub static LOAD_BALANCING_ROUND_ROBIN: &str = "round-robin";
+
+pub static LOAD_BALANCING_LEAST_BUSY: &str = "least-busy";
+
+pub static MAX_REPLICAS: u32 = 64;
+
+pub static DEFAULT_MAX_QUEUE_SIZE: u32 = 16;
+
+pub static DEFAULT_QUEUE_TIMEOUT_SECS: u64 = 300;
+
+/// Response header which reports position of request in queue of model server
+pub static QUEUE_POSITION_HEADER: &str = "x-queue-position";
+
+pub static ON_DEMAND_START_TIMEOUT_SECS: u64 = 600;
+
+/// Seconds to wait for model server started on demand to be registered
+pub static ON_DEMAND_REGISTER_TIMEOUT_SECS: u64 = 10;
+
+pub static GATEWAY_MAX_PAYLOAD_SIZE: usize = 100 * 1024 * 1024;
+
+/// Path of OpenAI compatible transcription which whisper.cpp server is serving on
+pub static WHISPER_INFERENCE_PATH: &str = "/v1/audio/transcriptions";
+
+/// Context length which backends launch with if it is not requested
+pub static MEMORY_DEFAULT_CONTEXT_LENGTH: u64 = 4096;
+
+pub static MEMORY_DEFAULT_BATCH_SIZE: u64 = 512;
+
+/// Runtime and compute buffers which don't depend on model size
+pub static MEMORY_OVERHEAD_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Compute buffer for each token in batch
+pub static MEMORY_BATCH_TOKEN_SIZE: u64 = 512 * 1024;
+
+/// Warning is returned when estimated memory exceeds this ratio of available memory
+pub static MEMORY_WARNING_RATIO: f64 = 0.8;
+
 pub static MODEL_SOURCE_HUGGINGFACE: &str = "huggingface";
 pub static MODEL_SOURCE_MODELSCOPE: &str = "modelscope";
 
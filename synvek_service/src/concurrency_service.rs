@@ -0,0 +1,232 @@
+use crate::common;
+use crate::model_service::ModelServiceArgs;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Concurrency settings of model server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConcurrencyLimit {
+    /// Requests served at same time
+    pub max_concurrency: usize,
+
+    /// Requests waiting in queue, request is rejected once queue is full
+    pub max_queue_size: usize,
+
+    /// Time a request may wait in queue
+    pub queue_timeout: Duration,
+}
+
+impl ConcurrencyLimit {
+    /// Limit of model server, none if model server has no concurrency limit
+    pub fn create(
+        max_concurrency: Option<u32>,
+        max_queue_size: Option<u32>,
+        queue_timeout: Option<u64>,
+    ) -> Option<Self> {
+        let max_concurrency = max_concurrency?;
+        Some(ConcurrencyLimit {
+            max_concurrency: max_concurrency.max(1) as usize,
+            max_queue_size: max_queue_size.unwrap_or(common::DEFAULT_MAX_QUEUE_SIZE) as usize,
+            queue_timeout: Duration::from_secs(
+                queue_timeout.unwrap_or(common::DEFAULT_QUEUE_TIMEOUT_SECS),
+            ),
+        })
+    }
+
+    pub fn from_args(args: &ModelServiceArgs) -> Option<Self> {
+        Self::create(args.max_concurrency, args.max_queue_size, args.queue_timeout)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConcurrencyError {
+    /// Queue is full, request is rejected right away
+    QueueFull(usize),
+
+    /// Request waited in queue longer than queue timeout
+    Timeout(usize),
+}
+
+impl std::fmt::Display for ConcurrencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConcurrencyError::QueueFull(max_queue_size) => {
+                write!(f, "Too many requests: queue of {} requests is full", max_queue_size)
+            }
+            ConcurrencyError::Timeout(queue_position) => write!(
+                f,
+                "Request timed out in queue at position {}",
+                queue_position
+            ),
+        }
+    }
+}
+
+/// Permit to serve request, slot is released once it is dropped
+pub struct ConcurrencyPermit {
+    _permit: OwnedSemaphorePermit,
+
+    /// Position in queue when request arrived, 0 if request is served right away
+    pub queue_position: usize,
+}
+
+/// Place of request in queue, it is left once request is served, rejected or cancelled
+struct QueueSlot<'a> {
+    queued: &'a AtomicUsize,
+    queue_position: usize,
+}
+
+impl<'a> QueueSlot<'a> {
+    fn new(queued: &'a AtomicUsize) -> Self {
+        let queue_position = queued.fetch_add(1, Ordering::SeqCst) + 1;
+        QueueSlot {
+            queued,
+            queue_position,
+        }
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Concurrency limiter with FIFO queue, semaphore of tokio hands out permits in order of waiting
+pub struct ConcurrencyLimiter {
+    limit: ConcurrencyLimit,
+    semaphore: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(limit: ConcurrencyLimit) -> Self {
+        ConcurrencyLimiter {
+            semaphore: Arc::new(Semaphore::new(limit.max_concurrency)),
+            limit,
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Count of requests waiting in queue
+    pub fn queued_requests(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    /// Wait for free slot, request is rejected if queue is full or it waits longer than timeout
+    pub async fn acquire(&self) -> Result<ConcurrencyPermit, ConcurrencyError> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            return Ok(ConcurrencyPermit {
+                _permit: permit,
+                queue_position: 0,
+            });
+        }
+        let queue_slot = QueueSlot::new(&self.queued);
+        let queue_position = queue_slot.queue_position;
+        if queue_position > self.limit.max_queue_size {
+            return Err(ConcurrencyError::QueueFull(self.limit.max_queue_size));
+        }
+        let result = tokio::time::timeout(
+            self.limit.queue_timeout,
+            self.semaphore.clone().acquire_owned(),
+        )
+        .await;
+        drop(queue_slot);
+        match result {
+            Ok(Ok(permit)) => Ok(ConcurrencyPermit {
+                _permit: permit,
+                queue_position,
+            }),
+            _ => Err(ConcurrencyError::Timeout(queue_position)),
+        }
+    }
+}
+
+/// Limiters of model servers, keyed by task id
+static GLOBAL_LIMITERS: OnceLock<Arc<Mutex<HashMap<String, Arc<ConcurrencyLimiter>>>>> =
+    OnceLock::new();
+
+fn init_limiters() -> Arc<Mutex<HashMap<String, Arc<ConcurrencyLimiter>>>> {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+/// Get limiter of model server, it is created again if limit is changed. Requests holding permits
+/// of old limiter keep them until they finish.
+pub fn get_limiter(task_id: &str, limit: ConcurrencyLimit) -> Arc<ConcurrencyLimiter> {
+    let limiters = GLOBAL_LIMITERS.get_or_init(|| init_limiters());
+    let mut limiters = limiters.lock().unwrap();
+    if let Some(limiter) = limiters.get(task_id) {
+        if limiter.limit == limit {
+            return limiter.clone();
+        }
+    }
+    let limiter = Arc::new(ConcurrencyLimiter::new(limit));
+    limiters.insert(task_id.to_string(), limiter.clone());
+    limiter
+}
+
+/// Count of requests waiting in queue of model server
+pub fn get_queued_requests(task_id: &str) -> usize {
+    let limiters = GLOBAL_LIMITERS.get_or_init(|| init_limiters());
+    let limiters = limiters.lock().unwrap();
+    limiters
+        .get(task_id)
+        .map(|limiter| limiter.queued_requests())
+        .unwrap_or(0)
+}
+
+pub fn remove_limiter(task_id: &str) {
+    let limiters = GLOBAL_LIMITERS.get_or_init(|| init_limiters());
+    let mut limiters = limiters.lock().unwrap();
+    limiters.remove(task_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_limiter(max_queue_size: usize, queue_timeout: Duration) -> Arc<ConcurrencyLimiter> {
+        Arc::new(ConcurrencyLimiter::new(ConcurrencyLimit {
+            max_concurrency: 1,
+            max_queue_size,
+            queue_timeout,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_queue_full() {
+        let limiter = create_limiter(1, Duration::from_secs(5));
+        let permit = limiter.acquire().await.unwrap();
+        assert_eq!(permit.queue_position, 0);
+        let waiting_limiter = limiter.clone();
+        let waiting = tokio::spawn(async move {
+            waiting_limiter
+                .acquire()
+                .await
+                .map(|permit| permit.queue_position)
+        });
+        while limiter.queued_requests() == 0 {
+            tokio::task::yield_now().await;
+        }
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(ConcurrencyError::QueueFull(1))
+        ));
+        drop(permit);
+        assert_eq!(waiting.await.unwrap().unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_queue_timeout() {
+        let limiter = create_limiter(1, Duration::from_millis(50));
+        let _permit = limiter.acquire().await.unwrap();
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(ConcurrencyError::Timeout(1))
+        ));
+        assert_eq!(limiter.queued_requests(), 0);
+    }
+}
@@ -16,14 +16,22 @@ pub struct SynvekConfig {
     #[serde(default = "default_port")]
     pub port: u16,
 
+    #[schemars(description = "Seconds to wait for model server to exit after terminate signal before it is killed")]
+    #[serde(default = "default_model_stop_timeout")]
+    pub model_stop_timeout: u64,
+
     #[schemars(description = "Synvek agent port")]
     #[serde(default = "default_agent_port")]
     pub agent_port: u16,
 
-    #[schemars(description = "First model server port number, followings will increase by 1")]
+    #[schemars(description = "First model server port number, free ports are allocated from here")]
     #[serde(default = "default_model_port")]
     pub model_port: u16,
 
+    #[schemars(description = "Number of ports from model_port which can be allocated to model servers")]
+    #[serde(default = "default_model_port_range")]
+    pub model_port_range: u16,
+
     #[schemars(description = "Host")]
     #[serde(default = "default_host")]
     pub host: String,
@@ -61,6 +69,14 @@ pub struct SynvekConfig {
     #[schemars(description = "Setup custom upscale model dir, default is $DATA_DIR/lora if not provided")]
     #[serde(default = "upscale_dir")]
     pub upscale_dir: Option<String>,
+
+    #[schemars(description = "Maximum number of files downloaded at same time across all download tasks")]
+    #[serde(default = "default_max_concurrent_downloads")]
+    pub max_concurrent_downloads: u32,
+
+    #[schemars(description = "Maximum download speed in bytes per second across all download tasks, 0 means unlimited")]
+    #[serde(default = "default_download_bandwidth_limit")]
+    pub download_bandwidth_limit: u64,
 }
 
 fn default_port() -> u16 {
@@ -71,6 +87,22 @@ fn default_model_port() -> u16 {
     12002
 }
 
+fn default_model_port_range() -> u16 {
+    1000
+}
+
+fn default_model_stop_timeout() -> u64 {
+    10
+}
+
+fn default_max_concurrent_downloads() -> u32 {
+    4
+}
+
+fn default_download_bandwidth_limit() -> u64 {
+    0
+}
+
 fn default_agent_port() -> u16 {
     12000
 }
@@ -117,6 +149,8 @@ impl Default for SynvekConfig {
             port: default_port(),
             agent_port: default_agent_port(),
             model_port: default_model_port(),
+            model_port_range: default_model_port_range(),
+            model_stop_timeout: default_model_stop_timeout(),
             host: default_host(),
             endpoint: default_endpoint(),
             multi_process: default_multi_process(),
@@ -126,6 +160,8 @@ impl Default for SynvekConfig {
             control_net_dir: control_net_dir(),
             embedding_dir: embedding_dir(),
             upscale_dir: upscale_dir(),
+            max_concurrent_downloads: default_max_concurrent_downloads(),
+            download_bandwidth_limit: default_download_bandwidth_limit(),
         }
     }
 }
@@ -163,6 +199,8 @@ fn update_synvek_config(synvek_config: SynvekConfig) {
     let mut config = config_ref.lock().unwrap();
     config.port = synvek_config.port;
     config.model_port = synvek_config.model_port;
+    config.model_port_range = synvek_config.model_port_range;
+    config.model_stop_timeout = synvek_config.model_stop_timeout;
     config.agent_port = synvek_config.agent_port;
     config.host = synvek_config.host;
     config.endpoint = synvek_config.endpoint;
@@ -172,6 +210,8 @@ fn update_synvek_config(synvek_config: SynvekConfig) {
     config.control_net_dir = synvek_config.control_net_dir;
     config.embedding_dir = synvek_config.embedding_dir;
     config.upscale_dir = synvek_config.upscale_dir;
+    config.max_concurrent_downloads = synvek_config.max_concurrent_downloads;
+    config.download_bandwidth_limit = synvek_config.download_bandwidth_limit;
 }
 
 pub fn initialize_synvek_config() {
@@ -247,6 +287,8 @@ impl Config {
             port: 12001,
             agent_port: 12000,
             model_port: 12002,
+            model_port_range: 1000,
+            model_stop_timeout: 10,
             host: "0.0.0.0".to_string(),
             endpoint: "https://huggingface.co".to_string(),
             multi_process: true,
@@ -256,6 +298,8 @@ impl Config {
             control_net_dir: None,
             embedding_dir: None,
             upscale_dir: None,
+            max_concurrent_downloads: 4,
+            download_bandwidth_limit: 0,
         }
     }
     fn from_working_dir() -> Self {
@@ -315,6 +359,12 @@ impl Config {
         if let Some(model_port) = new_config.get(common::CONFIG_MODEL_PORT) {
             config.model_port = model_port.as_u64().unwrap() as u16;
         }
+        if let Some(model_port_range) = new_config.get(common::CONFIG_MODEL_PORT_RANGE) {
+            config.model_port_range = model_port_range.as_u64().unwrap() as u16;
+        }
+        if let Some(model_stop_timeout) = new_config.get(common::CONFIG_MODEL_STOP_TIMEOUT) {
+            config.model_stop_timeout = model_stop_timeout.as_u64().unwrap();
+        }
         if let Some(agent_port) = new_config.get(common::CONFIG_AGENT_PORT) {
             config.agent_port = agent_port.as_u64().unwrap() as u16;
         }
@@ -345,6 +395,12 @@ impl Config {
         if let Some(upscale_dir) = new_config.get(common::UPSCALE_DIR) {
             config.upscale_dir = Some(upscale_dir.as_str().unwrap().to_owned());
         }
+        if let Some(max_concurrent_downloads) = new_config.get(common::CONFIG_MAX_CONCURRENT_DOWNLOADS) {
+            config.max_concurrent_downloads = max_concurrent_downloads.as_u64().unwrap() as u32;
+        }
+        if let Some(download_bandwidth_limit) = new_config.get(common::CONFIG_DOWNLOAD_BANDWIDTH_LIMIT) {
+            config.download_bandwidth_limit = download_bandwidth_limit.as_u64().unwrap();
+        }
         config
     }
 
@@ -457,6 +513,37 @@ impl Config {
         config.model_port
     }
 
+    pub fn get_config_model_port_range(&self) -> u16 {
+        let config = get_synvek_config();
+        config.model_port_range
+    }
+
+    pub fn get_config_model_stop_timeout(&self) -> u64 {
+        let config = get_synvek_config();
+        config.model_stop_timeout
+    }
+
+    pub fn get_config_max_concurrent_downloads(&self) -> u32 {
+        let config = get_synvek_config();
+        config.max_concurrent_downloads
+    }
+
+    pub fn get_config_download_bandwidth_limit(&self) -> u64 {
+        let config = get_synvek_config();
+        config.download_bandwidth_limit
+    }
+
+    /// Change download speed limit at runtime and save it in config file
+    pub fn update_config_download_bandwidth_limit(&self, download_bandwidth_limit: u64) -> Result<()> {
+        {
+            let config_ref = Arc::clone(SYNVEK_CONFIG.get_or_init(|| init_synvek_config()));
+            let mut config = config_ref.lock().unwrap();
+            config.download_bandwidth_limit = download_bandwidth_limit;
+        }
+        fs::create_dir_all(self.config_dir.clone())?;
+        self.save_config()
+    }
+
     pub fn get_config_agent_port(&self) -> u16 {
         let config = get_synvek_config();
         config.agent_port
@@ -0,0 +1,460 @@
+use crate::common;
+use anyhow::anyhow;
+use futures::StreamExt;
+use hf_hub::api::Progress;
+use hf_hub::api::sync::symlink_or_rename;
+use reqwest::{Client, StatusCode, header};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::RefCell;
+use std::fs;
+use std::io::{Read, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::runtime;
+
+/// Chunk of file downloaded by one range request
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DownloadChunk {
+    /// Offset of first byte
+    pub start: u64,
+
+    /// Offset of last byte, inclusive same as range header
+    pub end: u64,
+
+    /// Bytes written from start, chunk is resumed from here
+    pub written: u64,
+}
+
+impl DownloadChunk {
+    fn size(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    fn is_finished(&self) -> bool {
+        self.written >= self.size()
+    }
+}
+
+/// Chunks of temp blob, it is saved next to temp blob so interrupted download can be resumed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ChunkMap {
+    pub etag: String,
+
+    pub size: u64,
+
+    pub chunk_size: u64,
+
+    pub chunks: Vec<DownloadChunk>,
+}
+
+impl ChunkMap {
+    pub fn new(etag: &str, size: u64, chunk_size: u64) -> Self {
+        let chunk_size = chunk_size.max(1);
+        let mut chunks: Vec<DownloadChunk> = vec![];
+        let mut start = 0;
+        while start < size {
+            let end = (start + chunk_size).min(size) - 1;
+            chunks.push(DownloadChunk {
+                start,
+                end,
+                written: 0,
+            });
+            start = end + 1;
+        }
+        ChunkMap {
+            etag: etag.to_string(),
+            size,
+            chunk_size,
+            chunks,
+        }
+    }
+
+    /// Bytes already written to temp blob
+    pub fn written(&self) -> u64 {
+        self.chunks.iter().map(|chunk| chunk.written).sum()
+    }
+
+    /// Whether saved chunk map belongs to same remote file and chunk layout
+    fn matches(&self, etag: &str, size: u64, chunk_size: u64) -> bool {
+        self.etag == etag && self.size == size && self.chunk_size == chunk_size
+    }
+}
+
+/// Progress of range download. Connections share one async runtime, so download waits for
+/// bandwidth on runtime and reports bytes without blocking.
+pub trait RangeProgress: Progress {
+    /// Reserve bytes of given size from bandwidth limits and return time to wait before download
+    fn reserve(&mut self, size: usize) -> Duration;
+
+    /// Same as update but it never waits for bandwidth
+    fn update_unthrottled(&mut self, size: usize) -> bool;
+}
+
+/// Remote file downloaded into hub cache layout: blob, snapshot pointer and revision ref
+pub struct RangeDownload {
+    pub url: String,
+
+    pub access_token: Option<String>,
+
+    pub etag: String,
+
+    pub commit_hash: String,
+
+    pub size: u64,
+
+    pub blob_path: PathBuf,
+
+    pub pointer_path: PathBuf,
+
+    pub ref_path: PathBuf,
+
+    pub file_name: String,
+}
+
+#[derive(Debug)]
+pub enum RangeDownloadError {
+    /// Server ignores range header, file is downloaded with single stream instead
+    RangeNotSupported,
+
+    /// Download is cancelled by progress, e.g. task is stopped
+    Cancelled,
+
+    Failed(anyhow::Error),
+}
+
+impl std::fmt::Display for RangeDownloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeDownloadError::RangeNotSupported => write!(f, "Range request is not supported"),
+            RangeDownloadError::Cancelled => write!(f, "Download is cancelled"),
+            RangeDownloadError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl<E: Into<anyhow::Error>> From<E> for RangeDownloadError {
+    fn from(e: E) -> Self {
+        RangeDownloadError::Failed(e.into())
+    }
+}
+
+fn get_part_path(blob_path: &Path) -> PathBuf {
+    let mut part_path = blob_path.as_os_str().to_owned();
+    part_path.push(".part");
+    PathBuf::from(part_path)
+}
+
+fn get_chunk_map_path(blob_path: &Path) -> PathBuf {
+    let mut chunk_map_path = blob_path.as_os_str().to_owned();
+    chunk_map_path.push(".chunks.json");
+    PathBuf::from(chunk_map_path)
+}
+
+/// Load chunk map of interrupted download, new chunk map is created if temp blob can't be resumed
+fn load_chunk_map(download: &RangeDownload, chunk_size: u64) -> ChunkMap {
+    let part_path = get_part_path(&download.blob_path);
+    let chunk_map_path = get_chunk_map_path(&download.blob_path);
+    let part_size = fs::metadata(&part_path).map(|metadata| metadata.len()).ok();
+    if part_size == Some(download.size) {
+        let chunk_map = fs::read_to_string(&chunk_map_path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<ChunkMap>(&data).ok());
+        if let Some(chunk_map) = chunk_map {
+            if chunk_map.matches(download.etag.as_str(), download.size, chunk_size) {
+                tracing::info!(
+                    "Resume download of file: {} from {} bytes",
+                    download.file_name,
+                    chunk_map.written()
+                );
+                return chunk_map;
+            }
+        }
+    }
+    ChunkMap::new(download.etag.as_str(), download.size, chunk_size)
+}
+
+fn save_chunk_map(blob_path: &Path, chunk_map: &ChunkMap) -> anyhow::Result<()> {
+    let json = serde_json::to_string(chunk_map)?;
+    fs::write(get_chunk_map_path(blob_path), json)?;
+    Ok(())
+}
+
+/// Whether etag is sha256 of file content, it is the case for LFS files on Hugging Face
+fn is_sha256_etag(etag: &str) -> bool {
+    etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn get_file_sha256(path: &Path) -> anyhow::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let count = file.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        hasher.update(&buffer[..count]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Check assembled temp blob before it is moved into cache. Content is checked against etag if it
+/// is sha256, otherwise all chunks must be written and size must match. Temp blob and chunk map
+/// are removed on mismatch so next attempt downloads file again.
+fn verify_blob(
+    download: &RangeDownload,
+    part_path: &Path,
+    chunk_map: &ChunkMap,
+) -> Result<(), RangeDownloadError> {
+    let part_size = fs::metadata(part_path)?.len();
+    let mismatch = if part_size != download.size || chunk_map.written() != download.size {
+        Some(format!(
+            "{} bytes written of {} bytes",
+            chunk_map.written(),
+            download.size
+        ))
+    } else if is_sha256_etag(download.etag.as_str()) {
+        let sha256 = get_file_sha256(part_path)?;
+        if sha256.eq_ignore_ascii_case(download.etag.as_str()) {
+            None
+        } else {
+            Some(format!("sha256 {} doesn't match {}", sha256, download.etag))
+        }
+    } else {
+        None
+    };
+    if let Some(mismatch) = mismatch {
+        let _ = fs::remove_file(part_path);
+        let _ = fs::remove_file(get_chunk_map_path(&download.blob_path));
+        return Err(RangeDownloadError::Failed(anyhow!(
+            "Downloaded file: {} is corrupted with {}",
+            download.file_name,
+            mismatch
+        )));
+    }
+    Ok(())
+}
+
+/// Write finished blob into cache: snapshot pointer links to blob and ref points to commit
+fn finish_blob(download: &RangeDownload) -> anyhow::Result<()> {
+    if let Some(parent) = download.pointer_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Some(parent) = download.ref_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    symlink_or_rename(&download.blob_path, &download.pointer_path)?;
+    fs::write(&download.ref_path, download.commit_hash.as_str())?;
+    Ok(())
+}
+
+/// Download file by parallel range requests into preallocated temp blob. Finished chunks are
+/// recorded in chunk map, so download is resumed after connection drops or service restarts.
+pub fn download_with_ranges<P: RangeProgress>(
+    download: &RangeDownload,
+    mut progress: P,
+) -> Result<(), RangeDownloadError> {
+    progress.init(download.size as usize, download.file_name.as_str());
+    if download.blob_path.exists() {
+        finish_blob(download)?;
+        progress.finish();
+        return Ok(());
+    }
+    if let Some(parent) = download.blob_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let chunk_map = load_chunk_map(download, common::RANGE_DOWNLOAD_CHUNK_SIZE);
+    let part_path = get_part_path(&download.blob_path);
+    let part_file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&part_path)?;
+    part_file.set_len(download.size)?;
+    drop(part_file);
+    save_chunk_map(&download.blob_path, &chunk_map)?;
+    let written = chunk_map.written() as usize;
+    // Bytes downloaded before are reported without waiting for bandwidth
+    if written > 0 && !progress.update_unthrottled(written) {
+        return Err(RangeDownloadError::Cancelled);
+    }
+
+    let rt = runtime::Builder::new_current_thread().enable_all().build()?;
+    let progress = RefCell::new(progress);
+    let chunk_map = RefCell::new(chunk_map);
+    rt.block_on(download_chunks(download, &part_path, &chunk_map, &progress))?;
+
+    verify_blob(download, &part_path, &chunk_map.borrow())?;
+    fs::rename(&part_path, &download.blob_path)?;
+    let _ = fs::remove_file(get_chunk_map_path(&download.blob_path));
+    finish_blob(download)?;
+    progress.into_inner().finish();
+    Ok(())
+}
+
+async fn download_chunks<P: RangeProgress>(
+    download: &RangeDownload,
+    part_path: &Path,
+    chunk_map: &RefCell<ChunkMap>,
+    progress: &RefCell<P>,
+) -> Result<(), RangeDownloadError> {
+    let client = Client::builder()
+        .connect_timeout(std::time::Duration::from_secs(30))
+        .build()?;
+    let pending_chunks: Vec<usize> = chunk_map
+        .borrow()
+        .chunks
+        .iter()
+        .enumerate()
+        .filter(|(_, chunk)| !chunk.is_finished())
+        .map(|(index, _)| index)
+        .collect();
+    let mut results = futures::stream::iter(pending_chunks.into_iter().map(|index| {
+        download_chunk_with_retry(&client, download, part_path, chunk_map, progress, index)
+    }))
+    .buffer_unordered(common::RANGE_DOWNLOAD_CONNECTIONS);
+    while let Some(result) = results.next().await {
+        // The first failure stops download, finished chunks are kept for next attempt
+        result?;
+    }
+    Ok(())
+}
+
+async fn download_chunk_with_retry<P: RangeProgress>(
+    client: &Client,
+    download: &RangeDownload,
+    part_path: &Path,
+    chunk_map: &RefCell<ChunkMap>,
+    progress: &RefCell<P>,
+    index: usize,
+) -> Result<(), RangeDownloadError> {
+    let mut retry_count = 0;
+    loop {
+        let result = download_chunk(client, download, part_path, chunk_map, progress, index).await;
+        match result {
+            Err(RangeDownloadError::Failed(e)) if retry_count < common::RANGE_DOWNLOAD_RETRY_COUNT => {
+                retry_count += 1;
+                tracing::warn!(
+                    "Retry chunk {} of file: {} for {} times with error: {}",
+                    index,
+                    download.file_name,
+                    retry_count,
+                    e
+                );
+                tokio::time::sleep(std::time::Duration::from_secs(retry_count as u64)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Download rest of chunk from its written offset
+async fn download_chunk<P: RangeProgress>(
+    client: &Client,
+    download: &RangeDownload,
+    part_path: &Path,
+    chunk_map: &RefCell<ChunkMap>,
+    progress: &RefCell<P>,
+    index: usize,
+) -> Result<(), RangeDownloadError> {
+    let chunk = chunk_map.borrow().chunks[index].clone();
+    let mut request = client.get(download.url.as_str()).header(
+        header::RANGE,
+        format!("bytes={}-{}", chunk.start + chunk.written, chunk.end),
+    );
+    if let Some(access_token) = download.access_token.as_ref() {
+        request = request.bearer_auth(access_token);
+    }
+    let mut response = request.send().await?;
+    if response.status() == StatusCode::OK {
+        return Err(RangeDownloadError::RangeNotSupported);
+    }
+    if response.status() != StatusCode::PARTIAL_CONTENT {
+        return Err(RangeDownloadError::Failed(anyhow!(
+            "Unexpected status {} on range request of file: {}",
+            response.status(),
+            download.file_name
+        )));
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .write(true)
+        .open(part_path)
+        .await?;
+    file.seek(SeekFrom::Start(chunk.start + chunk.written)).await?;
+    let mut written = chunk.written;
+    let result: Result<(), RangeDownloadError> = async {
+        while let Some(bytes) = response.chunk().await? {
+            let remaining = (chunk.size() - written) as usize;
+            let bytes = &bytes[..bytes.len().min(remaining)];
+            let wait = progress.borrow_mut().reserve(bytes.len());
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+            file.write_all(bytes).await?;
+            written += bytes.len() as u64;
+            if !progress.borrow_mut().update_unthrottled(bytes.len()) {
+                return Err(RangeDownloadError::Cancelled);
+            }
+            if written >= chunk.size() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    .await;
+    // Offset is recorded only after its bytes are synced, chunk map saved by other chunks must not
+    // point past data on disk
+    file.flush().await?;
+    file.sync_data().await?;
+    chunk_map.borrow_mut().chunks[index].written = written;
+    save_chunk_map(&download.blob_path, &chunk_map.borrow())?;
+    result?;
+    if written < chunk.size() {
+        return Err(RangeDownloadError::Failed(anyhow!(
+            "Connection closed at {} of {} bytes in chunk {} of file: {}",
+            written,
+            chunk.size(),
+            index,
+            download.file_name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_chunk_map() {
+        let chunk_map = ChunkMap::new("etag", 10, 4);
+        let ranges: Vec<(u64, u64)> = chunk_map
+            .chunks
+            .iter()
+            .map(|chunk| (chunk.start, chunk.end))
+            .collect();
+        assert_eq!(ranges, vec![(0, 3), (4, 7), (8, 9)]);
+        assert_eq!(chunk_map.written(), 0);
+        assert!(chunk_map.matches("etag", 10, 4));
+        assert!(!chunk_map.matches("etag", 10, 8));
+    }
+
+    #[test]
+    fn test_sha256_etag() {
+        assert!(is_sha256_etag(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        ));
+        assert!(!is_sha256_etag("d41d8cd98f00b204e9800998ecf8427e"));
+    }
+
+    #[test]
+    fn test_chunk_finished() {
+        let mut chunk_map = ChunkMap::new("etag", 10, 4);
+        chunk_map.chunks[2].written = 2;
+        assert!(chunk_map.chunks[2].is_finished());
+        assert!(!chunk_map.chunks[0].is_finished());
+        assert_eq!(chunk_map.written(), 2);
+    }
+}
@@ -0,0 +1,452 @@
+use crate::fetch_service::{RunningTaskItem, Task};
+use crate::{common, config, fetch_service};
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Task in download queue, it is left once all of its files are finished or it is stopped
+struct QueuedTask {
+    /// Task of same name may be queued again once it is stopped, so files are tracked by queue id
+    queue_id: u64,
+    task: Task,
+    priority: i32,
+    lora_model: bool,
+    download_concurrency: usize,
+    pending_task_items: VecDeque<RunningTaskItem>,
+    active_count: usize,
+}
+
+/// Place of task in download queue
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueStatus {
+    /// Position in queue, starting from 1
+    pub queue_position: usize,
+
+    /// Task is waiting for free download slot
+    pub queued: bool,
+
+    pub priority: i32,
+
+    /// Files of task being downloaded
+    pub active_count: usize,
+}
+
+/// File handed out to download worker
+struct QueuedTaskItem {
+    queue_id: u64,
+    task: Task,
+    lora_model: bool,
+    running_task_item: RunningTaskItem,
+}
+
+/// Download queue ordered by priority, files are handed out from first task with free slot
+#[derive(Default)]
+struct DownloadQueue {
+    next_queue_id: u64,
+    active_count: usize,
+    tasks: Vec<QueuedTask>,
+}
+
+impl DownloadQueue {
+    /// Task is placed after tasks of same or higher priority
+    fn insert(&mut self, mut queued_task: QueuedTask) {
+        self.next_queue_id += 1;
+        queued_task.queue_id = self.next_queue_id;
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.priority < queued_task.priority)
+            .unwrap_or(self.tasks.len());
+        self.tasks.insert(index, queued_task);
+    }
+
+    fn contains(&self, queue_id: u64) -> bool {
+        self.tasks.iter().any(|task| task.queue_id == queue_id)
+    }
+
+    fn remove(&mut self, task_name: &str) -> bool {
+        let count = self.tasks.len();
+        self.tasks.retain(|task| task.task.task_name != task_name);
+        self.tasks.len() != count
+    }
+
+    /// Move task to position starting from 1, position beyond queue moves it to end
+    fn move_task(&mut self, task_name: &str, position: usize) -> bool {
+        let index = self
+            .tasks
+            .iter()
+            .position(|task| task.task.task_name == task_name);
+        if let Some(index) = index {
+            let queued_task = self.tasks.remove(index);
+            let position = position.max(1).min(self.tasks.len() + 1);
+            self.tasks.insert(position - 1, queued_task);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next_task_item(&mut self, max_concurrent_downloads: usize) -> Option<QueuedTaskItem> {
+        if self.active_count >= max_concurrent_downloads {
+            return None;
+        }
+        let queued_task = self.tasks.iter_mut().find(|task| {
+            task.pending_task_items.len() > 0 && task.active_count < task.download_concurrency
+        })?;
+        let running_task_item = queued_task.pending_task_items.pop_front()?;
+        queued_task.active_count += 1;
+        self.active_count += 1;
+        Some(QueuedTaskItem {
+            queue_id: queued_task.queue_id,
+            task: queued_task.task.clone(),
+            lora_model: queued_task.lora_model,
+            running_task_item,
+        })
+    }
+
+    /// Release download slot of file, name of task is returned if task left queue
+    fn finish_task_item(&mut self, queue_id: u64) -> Option<String> {
+        self.active_count = self.active_count.saturating_sub(1);
+        let index = self.tasks.iter().position(|task| task.queue_id == queue_id);
+        if let Some(index) = index {
+            let queued_task = &mut self.tasks[index];
+            queued_task.active_count = queued_task.active_count.saturating_sub(1);
+            if queued_task.active_count == 0 && queued_task.pending_task_items.len() == 0 {
+                tracing::info!("Task {} left download queue", queued_task.task.task_name);
+                return Some(self.tasks.remove(index).task.task_name);
+            }
+        }
+        None
+    }
+
+    fn get_status(&self, task_name: &str) -> Option<QueueStatus> {
+        self.tasks
+            .iter()
+            .position(|task| task.task.task_name == task_name)
+            .map(|index| {
+                let queued_task = &self.tasks[index];
+                QueueStatus {
+                    queue_position: index + 1,
+                    queued: queued_task.active_count == 0,
+                    priority: queued_task.priority,
+                    active_count: queued_task.active_count,
+                }
+            })
+    }
+}
+
+/// Token bucket of download speed. Bytes may be borrowed from next seconds, so large chunks wait
+/// longer instead of being split.
+struct BandwidthLimiter {
+    /// Bytes per second, 0 means unlimited
+    bandwidth_limit: u64,
+    available: f64,
+    updated_at: Instant,
+}
+
+impl BandwidthLimiter {
+    fn new(bandwidth_limit: u64) -> Self {
+        BandwidthLimiter {
+            bandwidth_limit,
+            available: bandwidth_limit as f64,
+            updated_at: Instant::now(),
+        }
+    }
+
+    fn set_bandwidth_limit(&mut self, bandwidth_limit: u64) {
+        self.bandwidth_limit = bandwidth_limit;
+        self.available = self.available.min(bandwidth_limit as f64);
+    }
+
+    /// Time to wait before bytes of given size may be downloaded
+    fn reserve(&mut self, size: usize, now: Instant) -> Duration {
+        if self.bandwidth_limit == 0 {
+            return Duration::ZERO;
+        }
+        let rate = self.bandwidth_limit as f64;
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.available = (self.available + elapsed * rate).min(rate);
+        self.updated_at = now;
+        self.available -= size as f64;
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / rate)
+        }
+    }
+}
+
+static DOWNLOAD_QUEUE: OnceLock<Arc<Mutex<DownloadQueue>>> = OnceLock::new();
+
+static GLOBAL_BANDWIDTH_LIMITER: OnceLock<Arc<Mutex<BandwidthLimiter>>> = OnceLock::new();
+
+/// Bandwidth limiters of tasks, keyed by task name
+static TASK_BANDWIDTH_LIMITERS: OnceLock<Arc<Mutex<HashMap<String, BandwidthLimiter>>>> =
+    OnceLock::new();
+
+fn init_global_bandwidth_limiter() -> Arc<Mutex<BandwidthLimiter>> {
+    let bandwidth_limit = config::Config::new().get_config_download_bandwidth_limit();
+    Arc::new(Mutex::new(BandwidthLimiter::new(bandwidth_limit)))
+}
+
+fn init_task_bandwidth_limiters() -> Arc<Mutex<HashMap<String, BandwidthLimiter>>> {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn init_download_queue() -> Arc<Mutex<DownloadQueue>> {
+    Arc::new(Mutex::new(DownloadQueue::default()))
+}
+
+fn get_download_queue() -> Arc<Mutex<DownloadQueue>> {
+    Arc::clone(DOWNLOAD_QUEUE.get_or_init(|| init_download_queue()))
+}
+
+/// Add files of task to download queue, they are downloaded once download slots are free
+pub fn enqueue_task(task: &Task, running_task_items: Vec<RunningTaskItem>, lora_model: bool) {
+    let priority = task.priority.unwrap_or(common::DEFAULT_DOWNLOAD_PRIORITY);
+    tracing::info!(
+        "Task {} is queued with priority {} and {} files",
+        task.task_name,
+        priority,
+        running_task_items.len()
+    );
+    let queued_task = QueuedTask {
+        queue_id: 0,
+        task: task.clone(),
+        priority,
+        lora_model,
+        download_concurrency: fetch_service::get_download_concurrency(task),
+        pending_task_items: VecDeque::from(running_task_items),
+        active_count: 0,
+    };
+    {
+        let download_queue = get_download_queue();
+        let mut download_queue = download_queue.lock().unwrap();
+        download_queue.remove(task.task_name.as_str());
+        download_queue.insert(queued_task);
+    }
+    set_task_bandwidth_limit(task.task_name.as_str(), task.bandwidth_limit);
+    dispatch_task_items();
+}
+
+/// Remove task from download queue, files being downloaded stop once task is stopped
+pub fn dequeue_task(task_name: &str) -> bool {
+    set_task_bandwidth_limit(task_name, None);
+    let download_queue = get_download_queue();
+    let mut download_queue = download_queue.lock().unwrap();
+    download_queue.remove(task_name)
+}
+
+/// Whether task queued with given queue id is still in download queue, workers of task stop once it
+/// is stopped even if task of same name is queued again
+pub fn is_queued(queue_id: u64) -> bool {
+    let download_queue = get_download_queue();
+    let download_queue = download_queue.lock().unwrap();
+    download_queue.contains(queue_id)
+}
+
+pub fn reorder_task(task_name: &str, position: usize) -> bool {
+    let download_queue = get_download_queue();
+    let mut download_queue = download_queue.lock().unwrap();
+    download_queue.move_task(task_name, position)
+}
+
+/// Move task to head of download queue, it gets next free download slot
+pub fn promote_task(task_name: &str) -> bool {
+    reorder_task(task_name, 1)
+}
+
+pub fn get_queue_status(task_name: &str) -> Option<QueueStatus> {
+    let download_queue = get_download_queue();
+    let download_queue = download_queue.lock().unwrap();
+    download_queue.get_status(task_name)
+}
+
+/// Global download speed in bytes per second, 0 means unlimited
+pub fn get_bandwidth_limit() -> u64 {
+    let limiter = GLOBAL_BANDWIDTH_LIMITER.get_or_init(|| init_global_bandwidth_limiter());
+    limiter.lock().unwrap().bandwidth_limit
+}
+
+/// Change global download speed, it is saved in config and applied to running downloads
+pub fn update_bandwidth_limit(bandwidth_limit: u64) -> Result<()> {
+    config::Config::new().update_config_download_bandwidth_limit(bandwidth_limit)?;
+    let limiter = GLOBAL_BANDWIDTH_LIMITER.get_or_init(|| init_global_bandwidth_limiter());
+    limiter.lock().unwrap().set_bandwidth_limit(bandwidth_limit);
+    Ok(())
+}
+
+/// Change download speed of task, none or 0 removes limit of task
+pub fn set_task_bandwidth_limit(task_name: &str, bandwidth_limit: Option<u64>) {
+    let limiters = TASK_BANDWIDTH_LIMITERS.get_or_init(|| init_task_bandwidth_limiters());
+    let mut limiters = limiters.lock().unwrap();
+    match bandwidth_limit.filter(|bandwidth_limit| *bandwidth_limit > 0) {
+        Some(bandwidth_limit) => {
+            if let Some(limiter) = limiters.get_mut(task_name) {
+                limiter.set_bandwidth_limit(bandwidth_limit);
+            } else {
+                limiters.insert(task_name.to_string(), BandwidthLimiter::new(bandwidth_limit));
+            }
+        }
+        None => {
+            limiters.remove(task_name);
+        }
+    }
+}
+
+/// Reserve bytes of given size from global and task bandwidth limits, time to wait before they are
+/// downloaded is returned
+pub fn reserve_download(task_name: &str, size: usize) -> Duration {
+    let now = Instant::now();
+    let global_wait = {
+        let limiter = GLOBAL_BANDWIDTH_LIMITER.get_or_init(|| init_global_bandwidth_limiter());
+        limiter.lock().unwrap().reserve(size, now)
+    };
+    let task_wait = {
+        let limiters = TASK_BANDWIDTH_LIMITERS.get_or_init(|| init_task_bandwidth_limiters());
+        let mut limiters = limiters.lock().unwrap();
+        limiters
+            .get_mut(task_name)
+            .map(|limiter| limiter.reserve(size, now))
+            .unwrap_or(Duration::ZERO)
+    };
+    global_wait.max(task_wait)
+}
+
+/// Block download of task until bytes of given size fit into bandwidth limits, it is used by
+/// blocking download only since it sleeps on current thread
+pub fn throttle_download(task_name: &str, size: usize) {
+    let wait = reserve_download(task_name, size);
+    if !wait.is_zero() {
+        std::thread::sleep(wait);
+    }
+}
+
+/// Start downloading files until download slots are used up
+fn dispatch_task_items() {
+    let max_concurrent_downloads = config::Config::new().get_config_max_concurrent_downloads() as usize;
+    loop {
+        let queued_task_item = {
+            let download_queue = get_download_queue();
+            let mut download_queue = download_queue.lock().unwrap();
+            download_queue.next_task_item(max_concurrent_downloads.max(1))
+        };
+        let Some(queued_task_item) = queued_task_item else {
+            break;
+        };
+        std::thread::spawn(move || {
+            fetch_service::fetch_running_task_item(
+                &queued_task_item.task,
+                queued_task_item.running_task_item,
+                queued_task_item.lora_model,
+                queued_task_item.queue_id,
+            );
+            let finished_task_name = {
+                let download_queue = get_download_queue();
+                let mut download_queue = download_queue.lock().unwrap();
+                download_queue.finish_task_item(queued_task_item.queue_id)
+            };
+            if let Some(finished_task_name) = finished_task_name {
+                set_task_bandwidth_limit(finished_task_name.as_str(), None);
+            }
+            dispatch_task_items();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_queued_task(task_name: &str, priority: i32, files: usize) -> QueuedTask {
+        let mut task = Task::default();
+        task.task_name = task_name.to_string();
+        QueuedTask {
+            queue_id: 0,
+            task,
+            priority,
+            lora_model: false,
+            download_concurrency: 2,
+            pending_task_items: (0..files).map(|_| RunningTaskItem::default()).collect(),
+            active_count: 0,
+        }
+    }
+
+    fn get_task_names(download_queue: &DownloadQueue) -> Vec<String> {
+        download_queue
+            .tasks
+            .iter()
+            .map(|task| task.task.task_name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_queue_order() {
+        let mut download_queue = DownloadQueue::default();
+        download_queue.insert(create_queued_task("a", 0, 1));
+        download_queue.insert(create_queued_task("b", 0, 1));
+        download_queue.insert(create_queued_task("c", 5, 1));
+        assert_eq!(get_task_names(&download_queue), vec!["c", "a", "b"]);
+        assert!(download_queue.move_task("b", 1));
+        assert_eq!(get_task_names(&download_queue), vec!["b", "c", "a"]);
+        assert!(download_queue.move_task("b", 10));
+        assert_eq!(get_task_names(&download_queue), vec!["c", "a", "b"]);
+        assert!(!download_queue.move_task("d", 1));
+        assert_eq!(download_queue.get_status("a").unwrap().queue_position, 2);
+    }
+
+    #[test]
+    fn test_download_slots() {
+        let mut download_queue = DownloadQueue::default();
+        download_queue.insert(create_queued_task("a", 0, 3));
+        download_queue.insert(create_queued_task("b", 0, 1));
+        let first = download_queue.next_task_item(3).unwrap();
+        let second = download_queue.next_task_item(3).unwrap();
+        let third = download_queue.next_task_item(3).unwrap();
+        assert_eq!(first.task.task_name, "a");
+        assert_eq!(second.task.task_name, "a");
+        assert_eq!(third.task.task_name, "b");
+        assert!(download_queue.next_task_item(3).is_none());
+        download_queue.finish_task_item(third.queue_id);
+        assert!(download_queue.get_status("b").is_none());
+        assert!(download_queue.next_task_item(3).is_none());
+        download_queue.finish_task_item(first.queue_id);
+        let fourth = download_queue.next_task_item(3).unwrap();
+        assert_eq!(fourth.task.task_name, "a");
+        assert!(!download_queue.get_status("a").unwrap().queued);
+    }
+
+    #[test]
+    fn test_requeued_task() {
+        let mut download_queue = DownloadQueue::default();
+        download_queue.insert(create_queued_task("a", 0, 2));
+        let first = download_queue.next_task_item(3).unwrap();
+        assert!(download_queue.contains(first.queue_id));
+        assert!(download_queue.remove("a"));
+        download_queue.insert(create_queued_task("a", 0, 2));
+        assert!(!download_queue.contains(first.queue_id));
+        assert!(download_queue.finish_task_item(first.queue_id).is_none());
+        let second = download_queue.next_task_item(3).unwrap();
+        assert!(download_queue.contains(second.queue_id));
+    }
+
+    #[test]
+    fn test_bandwidth_limiter() {
+        let mut limiter = BandwidthLimiter::new(1000);
+        let now = limiter.updated_at;
+        assert_eq!(limiter.reserve(1000, now), Duration::ZERO);
+        assert_eq!(limiter.reserve(500, now), Duration::from_millis(500));
+        assert_eq!(
+            limiter.reserve(500, now + Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            limiter.reserve(1000, now + Duration::from_secs(3)),
+            Duration::ZERO
+        );
+        limiter.set_bandwidth_limit(0);
+        assert_eq!(limiter.reserve(1000, now), Duration::ZERO);
+    }
+}
@@ -2,7 +2,7 @@ use crate::common::ServiceRef;
 use crate::fetch_service::{FetchFile, FetchRepo};
 use crate::fetch_service::{RunningTask, Task, TaskItem};
 use crate::model_service::ModelServiceArgs;
-use crate::{fetch_helper, fetch_service};
+use crate::{download_service, fetch_helper, fetch_service};
 use crate::{file_service, model_service};
 use actix_web::middleware::Logger;
 use actix_web::web::Bytes;
@@ -34,6 +34,15 @@ pub struct StartFetchRequest {
     pub lora_model: bool,
 
     pub control_model: bool,
+
+    /// Files downloaded at same time
+    pub download_concurrency: Option<u32>,
+
+    /// Tasks of higher priority are downloaded first
+    pub priority: Option<i32>,
+
+    /// Download speed of task in bytes per second
+    pub bandwidth_limit: Option<u64>,
 }
 
 /// Response for Start Model Server
@@ -118,6 +127,14 @@ pub struct FetchStatusData {
     pub lora_model: bool,
     
     pub control_net: bool,
+
+    /// Task is waiting in download queue
+    pub queued: bool,
+
+    /// Position in download queue, starting from 1
+    pub queue_position: Option<usize>,
+
+    pub priority: Option<i32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -184,6 +201,8 @@ pub struct StopFetchResponse {
 #[derive(Debug, Deserialize)]
 pub struct ResumeFetchRequest {
     pub fetch_name: String,
+
+    pub priority: Option<i32>,
 }
 
 /// Response for Start Model Server
@@ -201,6 +220,58 @@ pub struct ResumeFetchResponse {
     /// Data
     pub data: Option<String>,
 }
+#[derive(Debug, Deserialize)]
+pub struct ReorderFetchRequest {
+    pub fetch_name: String,
+
+    /// New position in download queue, starting from 1
+    pub position: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromoteFetchRequest {
+    pub fetch_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BandwidthRequest {
+    /// Task of bandwidth limit, global bandwidth limit is used if it is not provided
+    pub fetch_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateBandwidthRequest {
+    /// Task of bandwidth limit, global bandwidth limit is changed if it is not provided
+    pub fetch_name: Option<String>,
+
+    /// Bytes per second, none or 0 means unlimited
+    pub bandwidth_limit: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BandwidthData {
+    /// Global bandwidth limit in bytes per second, 0 means unlimited
+    pub bandwidth_limit: u64,
+
+    pub task_bandwidth_limit: Option<u64>,
+}
+
+/// Response for Bandwidth
+#[derive(Debug, Serialize)]
+pub struct BandwidthResponse {
+    /// Status
+    pub success: bool,
+
+    /// Code
+    pub code: String,
+
+    /// Message
+    pub message: String,
+
+    /// Data
+    pub data: Option<BandwidthData>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct UpdateFetchRequest {
     pub fetch_name: String,
@@ -255,6 +326,9 @@ async fn start_fetch(req: web::Json<StartFetchRequest>) -> impl Responder {
         private_lora_model: false,
         control_model: req.control_model,
         private_control_model: false,
+        download_concurrency: req.download_concurrency,
+        priority: req.priority,
+        bandwidth_limit: req.bandwidth_limit,
     };
     if task.fetch_repos.len() > 0 {
         task.fetch_repos.iter_mut().for_each(|fetch_repo| {
@@ -396,6 +470,9 @@ async fn resume_fetch(req: web::Json<ResumeFetchRequest>) -> impl Responder {
     let mut message: String = "".to_string();
     let task = fetch_service::load_local_task(fetch_name.as_str());
     if let Some(mut task) = task {
+        if req.priority.is_some() {
+            task.priority = req.priority;
+        }
         let start_result = fetch_service::start_task(&mut task, false);
         if !start_result.is_ok() {
             success = false;
@@ -441,6 +518,107 @@ async fn update_fetch(req: web::Json<UpdateFetchRequest>) -> impl Responder {
     HttpResponse::Ok().json(response)
 }
 
+#[post("/fetch/reorder")]
+async fn reorder_fetch(req: web::Json<ReorderFetchRequest>) -> impl Responder {
+    let success = download_service::reorder_task(req.fetch_name.as_str(), req.position);
+    let code: String = "".to_string();
+    let message = if success {
+        "".to_string()
+    } else {
+        "Task not found in download queue".to_string()
+    };
+    let response = GenericFetchResponse {
+        success,
+        code,
+        message,
+        data: None,
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[post("/fetch/promote")]
+async fn promote_fetch(req: web::Json<PromoteFetchRequest>) -> impl Responder {
+    let success = download_service::promote_task(req.fetch_name.as_str());
+    let code: String = "".to_string();
+    let message = if success {
+        "".to_string()
+    } else {
+        "Task not found in download queue".to_string()
+    };
+    let response = GenericFetchResponse {
+        success,
+        code,
+        message,
+        data: None,
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[post("/fetch/bandwidth/get")]
+async fn get_fetch_bandwidth(req: web::Json<BandwidthRequest>) -> impl Responder {
+    let mut success = true;
+    let code: String = "".to_string();
+    let mut message: String = "".to_string();
+    let mut data: Option<BandwidthData> = None;
+    let mut task_bandwidth_limit: Option<u64> = None;
+    if let Some(fetch_name) = req.fetch_name.clone() {
+        let task = fetch_service::load_local_task(fetch_name.as_str());
+        if let Some(task) = task {
+            task_bandwidth_limit = task.bandwidth_limit;
+        } else {
+            success = false;
+            message = "Task not found".to_string();
+        }
+    }
+    if success {
+        data = Some(BandwidthData {
+            bandwidth_limit: download_service::get_bandwidth_limit(),
+            task_bandwidth_limit,
+        });
+    }
+    let response = BandwidthResponse {
+        success,
+        code,
+        message,
+        data,
+    };
+    HttpResponse::Ok().json(response)
+}
+
+#[post("/fetch/bandwidth/update")]
+async fn update_fetch_bandwidth(req: web::Json<UpdateBandwidthRequest>) -> impl Responder {
+    let mut success = true;
+    let code: String = "".to_string();
+    let mut message: String = "".to_string();
+    let bandwidth_limit = req.bandwidth_limit.filter(|bandwidth_limit| *bandwidth_limit > 0);
+    if let Some(fetch_name) = req.fetch_name.clone() {
+        let task = fetch_service::load_local_task(fetch_name.as_str());
+        if let Some(mut task) = task {
+            task.bandwidth_limit = bandwidth_limit;
+            fetch_service::update_local_tasks(&task);
+            if download_service::get_queue_status(fetch_name.as_str()).is_some() {
+                download_service::set_task_bandwidth_limit(fetch_name.as_str(), bandwidth_limit);
+            }
+        } else {
+            success = false;
+            message = "Task not found".to_string();
+        }
+    } else {
+        let result = download_service::update_bandwidth_limit(bandwidth_limit.unwrap_or(0));
+        if let Err(err) = result {
+            success = false;
+            message = format!("Failed to save bandwidth limit: {}", err);
+        }
+    }
+    let response = GenericFetchResponse {
+        success,
+        code,
+        message,
+        data: None,
+    };
+    HttpResponse::Ok().json(response)
+}
+
 #[post("/fetch/list")]
 async fn list_fetch(req: web::Json<ListFetchRequest>) -> impl Responder {
     let mut success = true;
@@ -539,6 +717,10 @@ fn populate_fetch_status(
     mut fetch_status: &mut Vec<FetchStatusData>,
     running_task: &RunningTask,
 ) {
+    let queue_status = download_service::get_queue_status(fetch_name);
+    let queued = queue_status.as_ref().map(|status| status.queued).unwrap_or(false);
+    let queue_position = queue_status.as_ref().map(|status| status.queue_position);
+    let priority = queue_status.as_ref().map(|status| status.priority);
     running_task.finished_task_items.iter().for_each(|item| {
         let fetch_status_data = FetchStatusData {
             fetch_name: fetch_name.to_string(),
@@ -554,6 +736,9 @@ fn populate_fetch_status(
             error: None,
             lora_model: running_task.lora_model,
             control_net: running_task.control_model,
+            queued,
+            queue_position,
+            priority,
         };
         fetch_status.push(fetch_status_data);
     });
@@ -572,6 +757,9 @@ fn populate_fetch_status(
             error: item.error.clone(),
             lora_model: running_task.lora_model,
             control_net: running_task.control_model,
+            queued,
+            queue_position,
+            priority,
         };
         fetch_status.push(fetch_status_data);
     });
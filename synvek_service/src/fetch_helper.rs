@@ -1,6 +1,10 @@
-use crate::common::{MODEL_SOURCE_MODELSCOPE, MODELSCOPE_MODELS_DIR, MODELSCOPE_MODELS_DIR_PREFIX};
+use crate::common::{
+    MODEL_SOURCE_MODELSCOPE, MODELSCOPE_MODELS_DIR, MODELSCOPE_MODELS_DIR_PREFIX,
+    RANGE_DOWNLOAD_MIN_SIZE,
+};
+use crate::download_helper::{RangeDownload, RangeDownloadError, RangeProgress};
 use crate::fetch_api::ListFetchData;
-use crate::{fetch_helper, file_service, modelscope_helper, utils};
+use crate::{download_helper, fetch_helper, file_service, modelscope_helper, utils};
 use anyhow::anyhow;
 use hf_hub::api::sync::{symlink_or_rename, Api, ApiBuilder, ApiError, ApiRepo, Metadata};
 use hf_hub::api::{Progress, RepoInfo, Siblings};
@@ -413,7 +417,30 @@ pub fn create_link(src: &Path, dst: &Path
     }
 }
 
-pub fn download_model_file<P: Progress>(
+/// Download file by parallel range requests, false is returned if server doesn't support range
+/// request so file can be downloaded with single stream instead.
+fn download_model_file_with_ranges<P: RangeProgress>(
+    download: &RangeDownload,
+    progress: P,
+) -> anyhow::Result<bool> {
+    match download_helper::download_with_ranges(download, progress) {
+        Ok(()) => Ok(true),
+        Err(RangeDownloadError::RangeNotSupported) => {
+            tracing::warn!(
+                "Range request is not supported on: {}, download with single stream",
+                download.url
+            );
+            Ok(false)
+        }
+        Err(e) => Err(anyhow!(
+            "Error on download file: {} with error: {}",
+            download.file_name,
+            e
+        )),
+    }
+}
+
+pub fn download_model_file<P: RangeProgress + Clone>(
     model_source: &str,
     repo_name: &str,
     file_name: &str,
@@ -427,7 +454,7 @@ pub fn download_model_file<P: Progress>(
     let path = std::path::PathBuf::from(config.get_model_dir());
     let mut api_builder = ApiBuilder::new()
         .with_cache_dir(path)
-        .with_token(access_token);
+        .with_token(access_token.clone());
     if endpoint.is_some() && !endpoint.clone().unwrap().trim().is_empty() {
         api_builder = api_builder.with_endpoint(endpoint.unwrap());
     }
@@ -461,6 +488,22 @@ pub fn download_model_file<P: Progress>(
                 metadata.commit_hash.as_str(),
             );
             pointer_path.push(file_name);
+            if metadata.size as u64 >= RANGE_DOWNLOAD_MIN_SIZE {
+                let download = RangeDownload {
+                    url: url.clone(),
+                    access_token: access_token.clone(),
+                    etag: etag.clone(),
+                    commit_hash: metadata.commit_hash.clone(),
+                    size: metadata.size as u64,
+                    blob_path: blob_path.clone(),
+                    pointer_path: pointer_path.clone(),
+                    ref_path: ref_path.clone(),
+                    file_name: file_name.to_string(),
+                };
+                if download_model_file_with_ranges(&download, progress.clone())? {
+                    return Ok(());
+                }
+            }
             repo.download_external_with_progress(
                 url.as_str(),
                 metadata,
@@ -477,6 +520,41 @@ pub fn download_model_file<P: Progress>(
             ));
         }
     } else {
+        // Large file is downloaded by range requests into same cache layout as hf-hub
+        let url = repo.url(file_name);
+        let metadata = api.metadata(url.as_str());
+        if let Ok(metadata) = metadata {
+            if metadata.size as u64 >= RANGE_DOWNLOAD_MIN_SIZE {
+                let folder_name = Repo::with_revision(
+                    repo_name.to_string(),
+                    RepoType::Model,
+                    revision.to_string(),
+                )
+                .folder_name();
+                let mut pointer_path = modelscope_helper::get_pointer_path(
+                    folder_name.as_str(),
+                    metadata.commit_hash.as_str(),
+                );
+                pointer_path.push(file_name);
+                let download = RangeDownload {
+                    url: url.clone(),
+                    access_token: access_token.clone(),
+                    etag: metadata.etag.clone(),
+                    commit_hash: metadata.commit_hash.clone(),
+                    size: metadata.size as u64,
+                    blob_path: modelscope_helper::get_blob_path(
+                        folder_name.as_str(),
+                        metadata.etag.as_str(),
+                    ),
+                    pointer_path,
+                    ref_path: modelscope_helper::get_ref_path(folder_name.as_str(), revision),
+                    file_name: file_name.to_string(),
+                };
+                if download_model_file_with_ranges(&download, progress.clone())? {
+                    return Ok(());
+                }
+            }
+        }
         repo.download_with_progress(file_name, progress)?;
     }
     Ok(())
@@ -1,4 +1,4 @@
-use crate::{CACHE_REPO_FILES_SLEEP_DURATION, DOWNLOAD_RETRY_COUNT_LIMIT, MODEL_SOURCE_HUGGINGFACE, MODEL_SOURCE_MODELSCOPE, MODEL_SOURCES, common, fetch_helper, file_service, system_service, config};
+use crate::{CACHE_REPO_FILES_SLEEP_DURATION, DEFAULT_DOWNLOAD_CONCURRENCY, DOWNLOAD_RETRY_COUNT_LIMIT, MAX_DOWNLOAD_CONCURRENCY, MODEL_SOURCE_HUGGINGFACE, MODEL_SOURCE_MODELSCOPE, MODEL_SOURCES, common, download_service, fetch_helper, file_service, system_service, config};
 use anyhow::{Error, Result, anyhow};
 use hf_hub::api::Progress;
 use hf_hub::api::sync::Metadata;
@@ -8,6 +8,7 @@ use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant, SystemTime};
 use std::{fs, panic, thread};
 use crate::system_service::{MessageSource, MessageType};
+use crate::download_helper::RangeProgress;
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct FetchFile {
@@ -97,6 +98,15 @@ pub struct Task {
     pub control_model: bool,
     #[serde(default = "default_private_control_model")]
     pub private_control_model: bool,
+    /// Files downloaded at same time
+    #[serde(default)]
+    pub download_concurrency: Option<u32>,
+    /// Tasks of higher priority are downloaded first
+    #[serde(default)]
+    pub priority: Option<i32>,
+    /// Download speed of task in bytes per second
+    #[serde(default)]
+    pub bandwidth_limit: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -130,6 +140,8 @@ struct ProgressService {
     pub current_time: u128,
     pub speed: u64,
     pub retry_count: u64,
+    /// Download queue entry of worker, download is aborted once it leaves queue
+    pub queue_id: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -169,37 +181,13 @@ fn default_private_control_model() -> bool {
 static RUNNING_TASKS: OnceLock<Arc<Mutex<HashMap<String, RunningTask>>>> = OnceLock::new();
 static CACHE_REPO_FILES: OnceLock<Arc<Mutex<HashMap<String, CacheRepoFile>>>> = OnceLock::new();
 
-impl Progress for ProgressService {
-    fn init(&mut self, size: usize, file_name: &str) {
-        self.total_size = size;
-        self.current_size = 0;
-        self.start_time = SystemTime::now()
-            .duration_since(SystemTime::UNIX_EPOCH)
-            .unwrap()
-            .as_millis();
-        self.speed_check_time = self.start_time;
-        self.speed_check_size = 0;
-        update_running_task_item(
-            self.task_name.as_str(),
-            self.model_source.as_str(),
-            self.repo_name.as_str(),
-            self.file_name.as_str(),
-            self.revision.as_str(),
-            self.total_size as u64,
-            self.current_size as u64,
-            self.speed,
-            &None,
-            self.retry_count,
-        );
-        tracing::info!(
-            "task {} started on repo: {}, file name: {} ",
-            self.task_name,
-            self.repo_name.clone(),
-            self.file_name.clone()
-        );
-    }
-
-    fn update(&mut self, size: usize) -> bool {
+impl ProgressService {
+    /// Record downloaded bytes and report progress, false is returned if task is stopped
+    fn report(&mut self, size: usize) -> bool {
+        if !download_service::is_queued(self.queue_id) {
+            tracing::warn!("Task {} is stopped since it left download queue", self.task_name);
+            return false;
+        }
         self.current_size += size;
         self.current_time = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -240,6 +228,42 @@ impl Progress for ProgressService {
             self.retry_count,
         )
     }
+}
+
+impl Progress for ProgressService {
+    fn init(&mut self, size: usize, file_name: &str) {
+        self.total_size = size;
+        self.current_size = 0;
+        self.start_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        self.speed_check_time = self.start_time;
+        self.speed_check_size = 0;
+        update_running_task_item(
+            self.task_name.as_str(),
+            self.model_source.as_str(),
+            self.repo_name.as_str(),
+            self.file_name.as_str(),
+            self.revision.as_str(),
+            self.total_size as u64,
+            self.current_size as u64,
+            self.speed,
+            &None,
+            self.retry_count,
+        );
+        tracing::info!(
+            "task {} started on repo: {}, file name: {} ",
+            self.task_name,
+            self.repo_name.clone(),
+            self.file_name.clone()
+        );
+    }
+
+    fn update(&mut self, size: usize) -> bool {
+        download_service::throttle_download(self.task_name.as_str(), size);
+        self.report(size)
+    }
 
     fn finish(&mut self) {
         finish_running_task_item(
@@ -264,6 +288,16 @@ impl Progress for ProgressService {
     }
 }
 
+impl RangeProgress for ProgressService {
+    fn reserve(&mut self, size: usize) -> Duration {
+        download_service::reserve_download(self.task_name.as_str(), size)
+    }
+
+    fn update_unthrottled(&mut self, size: usize) -> bool {
+        self.report(size)
+    }
+}
+
 fn init_running_tasks() -> Arc<Mutex<HashMap<String, RunningTask>>> {
     Arc::new(Mutex::new(HashMap::new()))
 }
@@ -321,6 +355,7 @@ pub fn has_cache_repo_file(cache_key: &str) -> bool {
 }
 
 pub fn stop_task(task_name: &str) -> bool {
+    download_service::dequeue_task(task_name);
     let map_ref = Arc::clone(RUNNING_TASKS.get().unwrap());
     let mut map = map_ref.lock().unwrap();
     tracing::info!("Check tasks: {:?}", map);
@@ -526,6 +561,9 @@ pub fn start_fetch_repo(
             private_lora_model: false,
             control_model: false,
             private_control_model: false,
+            download_concurrency: None,
+            priority: None,
+            bandwidth_limit: None,
         };
         let fetch_repo: FetchRepo = FetchRepo {
             model_source: model_source.to_string(),
@@ -612,6 +650,9 @@ pub fn start_fetch_repo_file(
             private_lora_model: false,
             control_model: false,
             private_control_model: false,
+            download_concurrency: None,
+            priority: None,
+            bandwidth_limit: None,
         };
         let fetch_file: FetchFile = FetchFile {
             model_source: model_source.to_string(),
@@ -796,159 +837,11 @@ pub fn start_task(task: &mut Task, require_remote_meta: bool) -> Result<bool> {
     system_service::send_message(MessageSource::TaskService, MessageType::TaskAdded, "".to_string());
     if current_task.all_task_items.len() > 0 {
         if current_task.running_task_items.len() > 0 {
-            let mut updated_task = task.clone();
-            std::thread::spawn(move || {
-                tracing::info!("Thread started for task： {}", updated_task.task_name);
-                let mut retry_index = 0;
-                while current_task.running_task_items.len() > 0
-                    && has_running_task(current_task.task_name.as_str())
-                {
-                    let mut first_running_task_item =
-                        current_task.running_task_items.first().unwrap().clone();
-                    tracing::info!(
-                        "Thread running for repo: {} with file name: {} ",
-                        first_running_task_item.repo_name.clone(),
-                        first_running_task_item.file_name.clone()
-                    );
-                    let progress = ProgressService {
-                        task_name: updated_task.task_name.clone(),
-                        model_source: updated_task.model_source.clone(),
-                        repo_name: first_running_task_item.repo_name.clone(),
-                        file_name: first_running_task_item.file_name.clone(),
-                        revision: first_running_task_item.revision.clone(),
-                        current_size: 0,
-                        total_size: 0,
-                        start_time: 0,
-                        finish_time: 0,
-                        speed_check_size: 0,
-                        speed_check_time: 0,
-                        current_time: 0,
-                        speed: 0,
-                        retry_count: first_running_task_item.retry_count,
-                    };
-                    tracing::info!(
-                        "Task {} running and start fetching repo name: {} and file name: {}",
-                        updated_task.task_name,
-                        first_running_task_item.repo_name,
-                        first_running_task_item.file_name
-                    );
-                    //Notify fronted task is already running now
-                    update_running_task_item(
-                        updated_task.task_name.as_str(),
-                        first_running_task_item.model_source.as_str(),
-                        first_running_task_item.repo_name.as_str(),
-                        first_running_task_item.file_name.as_str(),
-                        first_running_task_item.revision.as_str(),
-                        0,
-                        0,
-                        0,
-                        &None,
-                        retry_index,
-                    );
-                    let download_result = fetch_helper::download_model_file(
-                        first_running_task_item.model_source.as_str(),
-                        first_running_task_item.repo_name.as_str(),
-                        first_running_task_item.file_name.as_str(),
-                        first_running_task_item.revision.as_str(),
-                        first_running_task_item.commit_hash.as_str(),
-                        updated_task.mirror.clone(),
-                        first_running_task_item.access_token.clone(),
-                        progress.clone(),
-                    );
-                    if download_result.is_err() {
-                        let error = download_result.unwrap_err();
-                        tracing::error!(
-                            "Task {} running and failed fetching model source: {}, repo name: {} and file name: {} with error: {}",
-                            updated_task.task_name,
-                            first_running_task_item.model_source,
-                            first_running_task_item.repo_name,
-                            first_running_task_item.file_name,
-                            error.to_string()
-                        );
-                        if retry_index < DOWNLOAD_RETRY_COUNT_LIMIT {
-                            retry_index = retry_index + 1;
-                            update_running_task_item(
-                                updated_task.task_name.as_str(),
-                                first_running_task_item.model_source.as_str(),
-                                first_running_task_item.repo_name.as_str(),
-                                first_running_task_item.file_name.as_str(),
-                                first_running_task_item.revision.as_str(),
-                                0,
-                                0,
-                                0,
-                                &Option::from(error.to_string()),
-                                retry_index,
-                            );
-                        } else {
-                            tracing::error!(
-                                "Task {} failed to fetch  model source: {}, repo name: {} and file name: {} for {} times and is terminated",
-                                updated_task.task_name,
-                                first_running_task_item.model_source,
-                                first_running_task_item.repo_name,
-                                first_running_task_item.file_name,
-                                DOWNLOAD_RETRY_COUNT_LIMIT
-                            );
-                            current_task.running_task_items.remove(0);
-                            retry_index = 0;
-                        }
-                    } else {
-                        tracing::info!(
-                            "Task {} running and finished fetching  model source: {}, repo name: {} and file name: {}",
-                            updated_task.task_name,
-                            first_running_task_item.model_source,
-                            first_running_task_item.repo_name,
-                            first_running_task_item.file_name
-                        );
-                        let finished_task_item = FinishedTaskItem {
-                            model_source: first_running_task_item.model_source.clone(),
-                            repo_name: first_running_task_item.repo_name.clone(),
-                            file_name: first_running_task_item.file_name.clone(),
-                            revision: first_running_task_item.revision.clone(),
-                            access_token: first_running_task_item.access_token.clone(),
-                            file_size: first_running_task_item.total_size,
-                            commit_hash: first_running_task_item.commit_hash.clone(),
-                        };
-                        update_finished_task_item(updated_task.task_name.as_str(), finished_task_item);
-                        current_task.running_task_items.remove(0);
-                        retry_index = 0;
-                        // update model file info cache
-                        let update_time = SystemTime::now()
-                            .duration_since(SystemTime::UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs();
-                        populate_cache_repo_file(
-                            first_running_task_item.model_source.as_str(),
-                            first_running_task_item.repo_name.as_str(),
-                            first_running_task_item.file_name.as_str(),
-                            first_running_task_item.revision.as_str(),
-                            first_running_task_item.commit_hash.as_str(),
-                            true,
-                            first_running_task_item.total_size,
-                            first_running_task_item.access_token,
-                            update_time,
-                        );
-                        //check and create lora link
-                        if current_task.lora_model {
-                            create_lora_link(
-                                first_running_task_item.model_source.as_str(),
-                                first_running_task_item.repo_name.as_str(),
-                                first_running_task_item.file_name.as_str(),
-                                first_running_task_item.revision.as_str(),
-                                first_running_task_item.commit_hash.as_str());
-                        }
-                        //Don't need this, we can assign file directly
-                        //check and create control net link
-                        // if current_task.control_model {
-                        //     create_control_link(
-                        //         first_running_task_item.model_source.as_str(),
-                        //         first_running_task_item.repo_name.as_str(),
-                        //         first_running_task_item.file_name.as_str(),
-                        //         first_running_task_item.revision.as_str(),
-                        //         first_running_task_item.commit_hash.as_str());
-                        // }
-                    }
-                }
-            });
+            download_service::enqueue_task(
+                task,
+                current_task.running_task_items.clone(),
+                current_task.lora_model,
+            );
         } else if current_task.all_task_items.len() == current_task.finished_task_items.len() {
             tracing::warn!("All files are already finished for task {}", task.task_name);
             return Err(anyhow!(
@@ -973,6 +866,167 @@ pub fn start_task(task: &mut Task, require_remote_meta: bool) -> Result<bool> {
     Ok(true)
 }
 
+/// Number of files of task downloaded at same time
+pub fn get_download_concurrency(task: &Task) -> usize {
+    task.download_concurrency
+        .unwrap_or(DEFAULT_DOWNLOAD_CONCURRENCY)
+        .clamp(1, MAX_DOWNLOAD_CONCURRENCY) as usize
+}
+
+/// Download file of task queued with given queue id, retries stop once the queue entry is gone so
+/// worker of stopped task doesn't download along with workers of resumed task
+pub fn fetch_running_task_item(
+    updated_task: &Task,
+    running_task_item: RunningTaskItem,
+    lora_model: bool,
+    queue_id: u64,
+) {
+    let mut retry_index = 0;
+    while download_service::is_queued(queue_id) {
+        tracing::info!(
+            "Thread running for repo: {} with file name: {} ",
+            running_task_item.repo_name.clone(),
+            running_task_item.file_name.clone()
+        );
+        let progress = ProgressService {
+            task_name: updated_task.task_name.clone(),
+            model_source: updated_task.model_source.clone(),
+            repo_name: running_task_item.repo_name.clone(),
+            file_name: running_task_item.file_name.clone(),
+            revision: running_task_item.revision.clone(),
+            current_size: 0,
+            total_size: 0,
+            start_time: 0,
+            finish_time: 0,
+            speed_check_size: 0,
+            speed_check_time: 0,
+            current_time: 0,
+            speed: 0,
+            retry_count: retry_index,
+            queue_id,
+        };
+        tracing::info!(
+            "Task {} running and start fetching repo name: {} and file name: {}",
+            updated_task.task_name,
+            running_task_item.repo_name,
+            running_task_item.file_name
+        );
+        //Notify fronted task is already running now
+        update_running_task_item(
+            updated_task.task_name.as_str(),
+            running_task_item.model_source.as_str(),
+            running_task_item.repo_name.as_str(),
+            running_task_item.file_name.as_str(),
+            running_task_item.revision.as_str(),
+            0,
+            0,
+            0,
+            &None,
+            retry_index,
+        );
+        let download_result = fetch_helper::download_model_file(
+            running_task_item.model_source.as_str(),
+            running_task_item.repo_name.as_str(),
+            running_task_item.file_name.as_str(),
+            running_task_item.revision.as_str(),
+            running_task_item.commit_hash.as_str(),
+            updated_task.mirror.clone(),
+            running_task_item.access_token.clone(),
+            progress.clone(),
+        );
+        if download_result.is_err() {
+            let error = download_result.unwrap_err();
+            tracing::error!(
+                "Task {} running and failed fetching model source: {}, repo name: {} and file name: {} with error: {}",
+                updated_task.task_name,
+                running_task_item.model_source,
+                running_task_item.repo_name,
+                running_task_item.file_name,
+                error.to_string()
+            );
+            if retry_index < DOWNLOAD_RETRY_COUNT_LIMIT {
+                retry_index = retry_index + 1;
+                update_running_task_item(
+                    updated_task.task_name.as_str(),
+                    running_task_item.model_source.as_str(),
+                    running_task_item.repo_name.as_str(),
+                    running_task_item.file_name.as_str(),
+                    running_task_item.revision.as_str(),
+                    0,
+                    0,
+                    0,
+                    &Option::from(error.to_string()),
+                    retry_index,
+                );
+            } else {
+                tracing::error!(
+                    "Task {} failed to fetch  model source: {}, repo name: {} and file name: {} for {} times and is terminated",
+                    updated_task.task_name,
+                    running_task_item.model_source,
+                    running_task_item.repo_name,
+                    running_task_item.file_name,
+                    DOWNLOAD_RETRY_COUNT_LIMIT
+                );
+                return;
+            }
+        } else {
+            tracing::info!(
+                "Task {} running and finished fetching  model source: {}, repo name: {} and file name: {}",
+                updated_task.task_name,
+                running_task_item.model_source,
+                running_task_item.repo_name,
+                running_task_item.file_name
+            );
+            let finished_task_item = FinishedTaskItem {
+                model_source: running_task_item.model_source.clone(),
+                repo_name: running_task_item.repo_name.clone(),
+                file_name: running_task_item.file_name.clone(),
+                revision: running_task_item.revision.clone(),
+                access_token: running_task_item.access_token.clone(),
+                file_size: running_task_item.total_size,
+                commit_hash: running_task_item.commit_hash.clone(),
+            };
+            update_finished_task_item(updated_task.task_name.as_str(), finished_task_item);
+            // update model file info cache
+            let update_time = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            populate_cache_repo_file(
+                running_task_item.model_source.as_str(),
+                running_task_item.repo_name.as_str(),
+                running_task_item.file_name.as_str(),
+                running_task_item.revision.as_str(),
+                running_task_item.commit_hash.as_str(),
+                true,
+                running_task_item.total_size,
+                running_task_item.access_token.clone(),
+                update_time,
+            );
+            //check and create lora link
+            if lora_model {
+                create_lora_link(
+                    running_task_item.model_source.as_str(),
+                    running_task_item.repo_name.as_str(),
+                    running_task_item.file_name.as_str(),
+                    running_task_item.revision.as_str(),
+                    running_task_item.commit_hash.as_str());
+            }
+            //Don't need this, we can assign file directly
+            //check and create control net link
+            // if current_task.control_model {
+            //     create_control_link(
+            //         running_task_item.model_source.as_str(),
+            //         running_task_item.repo_name.as_str(),
+            //         running_task_item.file_name.as_str(),
+            //         running_task_item.revision.as_str(),
+            //         running_task_item.commit_hash.as_str());
+            // }
+            return;
+        }
+    }
+}
+
 fn create_lora_link(model_source: &str, repo_name: &str, file_name: &str, revision: &str, commit_hash: &str) {
     let lora_file_path = fetch_helper::get_file_path_in_cache(model_source, repo_name, file_name, revision);
     if let Some(lora_file_path) = lora_file_path {
@@ -1157,6 +1211,9 @@ fn load_local_private_tasks(tasks: &mut Tasks) {
             private_lora_model: false,
             control_model: false,
             private_control_model: false,
+            download_concurrency: None,
+            priority: None,
+            bandwidth_limit: None,
         };
         tasks.tasks.push(task);
     }
@@ -1182,6 +1239,9 @@ fn load_local_private_lora_tasks(tasks: &mut Tasks) {
             private_lora_model: true,
             control_model: false,
             private_control_model: false,
+            download_concurrency: None,
+            priority: None,
+            bandwidth_limit: None,
         };
         tasks.tasks.push(task);
     }
@@ -1207,6 +1267,9 @@ fn load_local_private_control_tasks(tasks: &mut Tasks) {
             private_lora_model: false,
             control_model: false,
             private_control_model: true,
+            download_concurrency: None,
+            priority: None,
+            bandwidth_limit: None,
         };
         tasks.tasks.push(task);
     }
@@ -0,0 +1,283 @@
+use crate::concurrency_service::ConcurrencyError;
+use crate::{common, gateway_service};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, get, post};
+use actix_web::{HttpResponse, Responder};
+use async_stream::stream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::SystemTime;
+
+/// OpenAI compatible error body
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayErrorData {
+    pub message: String,
+
+    #[serde(rename = "type")]
+    pub error_type: String,
+
+    pub code: Option<String>,
+}
+
+/// OpenAI compatible error response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayErrorResponse {
+    pub error: GatewayErrorData,
+}
+
+/// OpenAI compatible model data
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayModelData {
+    pub id: String,
+
+    pub object: String,
+
+    pub created: u64,
+
+    pub owned_by: String,
+}
+
+/// OpenAI compatible model list response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GatewayModelsResponse {
+    pub object: String,
+
+    pub data: Vec<GatewayModelData>,
+}
+
+fn error_response(status: StatusCode, error_type: &str, message: String) -> HttpResponse {
+    let response = GatewayErrorResponse {
+        error: GatewayErrorData {
+            message,
+            error_type: error_type.to_string(),
+            code: None,
+        },
+    };
+    HttpResponse::build(status).json(response)
+}
+
+/// Forward JSON request to model server selected by model field
+async fn forward_request(req: HttpRequest, body: Bytes, path: &str) -> HttpResponse {
+    let request_data = serde_json::from_slice::<Value>(&body);
+    let model = match request_data {
+        Ok(request_data) => request_data
+            .get("model")
+            .and_then(|model| model.as_str())
+            .map(|model| model.to_string()),
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "invalid_request_error",
+                format!("Invalid request body: {}", e),
+            );
+        }
+    };
+    let Some(model) = model else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "Model is required".to_string(),
+        );
+    };
+    forward_model_request(req, body, path, model, "application/json".to_string()).await
+}
+
+/// Forward request to model server of model, the response is streamed back as it is so SSE
+/// completions are passed through chunk by chunk.
+async fn forward_model_request(
+    req: HttpRequest,
+    body: Bytes,
+    path: &str,
+    model: String,
+    request_content_type: String,
+) -> HttpResponse {
+    let Some(model_server) = gateway_service::resolve_model_server(model.as_str()).await else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            "invalid_request_error",
+            format!("Model {} is not running", model),
+        );
+    };
+    gateway_service::touch_model_server(&model_server);
+    let request_guard = gateway_service::ModelRequestGuard::new(&model_server);
+    let concurrency_permit = match gateway_service::acquire_model_server(&model_server).await {
+        Ok(concurrency_permit) => concurrency_permit,
+        Err(ConcurrencyError::QueueFull(max_queue_size)) => {
+            return error_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "rate_limit_error",
+                format!(
+                    "Model {} is busy and its queue of {} requests is full",
+                    model, max_queue_size
+                ),
+            );
+        }
+        Err(ConcurrencyError::Timeout(queue_position)) => {
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "server_error",
+                format!(
+                    "Model {} is busy and request timed out in queue at position {}",
+                    model, queue_position
+                ),
+            );
+        }
+    };
+    let queue_position = concurrency_permit
+        .as_ref()
+        .map(|concurrency_permit| concurrency_permit.queue_position)
+        .unwrap_or(0);
+    let model_server_address = gateway_service::get_model_server_url(&model_server, path);
+    tracing::debug!(
+        "Forward request of model {} to: {}",
+        model,
+        model_server_address
+    );
+    let client = match gateway_service::create_gateway_client() {
+        Ok(client) => client,
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "server_error",
+                format!("Failed to create gateway client: {}", e),
+            );
+        }
+    };
+    let mut request_builder = client
+        .post(model_server_address.clone())
+        .header(reqwest::header::CONTENT_TYPE, request_content_type)
+        .body(body.to_vec());
+    if let Some(authorization) = req.headers().get(AUTHORIZATION) {
+        if let Ok(authorization) = authorization.to_str() {
+            request_builder = request_builder.header(reqwest::header::AUTHORIZATION, authorization);
+        }
+    }
+    match request_builder.send().await {
+        Ok(mut response) => {
+            let status =
+                StatusCode::from_u16(response.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+            let content_type = response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .unwrap_or("application/json")
+                .to_string();
+            let response_stream = stream! {
+                // Request is counted until response is streamed completely or client is gone
+                let _request_guard = request_guard;
+                let _concurrency_permit = concurrency_permit;
+                loop {
+                    match response.chunk().await {
+                        Ok(Some(chunk)) => {
+                            yield Ok::<_, actix_web::Error>(chunk);
+                        }
+                        Ok(None) => break,
+                        Err(e) => {
+                            tracing::error!(
+                                "Failed to read response from model server: {} with error: {}",
+                                model_server_address,
+                                e
+                            );
+                            break;
+                        }
+                    }
+                }
+                // Streaming response may last longer than idle timeout
+                gateway_service::touch_model_server(&model_server);
+            };
+            HttpResponse::build(status)
+                .insert_header((CONTENT_TYPE, content_type))
+                .insert_header((common::QUEUE_POSITION_HEADER, queue_position.to_string()))
+                .streaming(response_stream)
+        }
+        Err(e) => {
+            tracing::error!(
+                "Failed to forward request to model server: {} with error: {}",
+                model_server_address,
+                e
+            );
+            error_response(
+                StatusCode::BAD_GATEWAY,
+                "server_error",
+                format!("Failed to forward request to model {}: {}", model, e),
+            )
+        }
+    }
+}
+
+#[post("/chat/completions")]
+async fn chat_completions(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/chat/completions").await
+}
+
+#[post("/completions")]
+async fn completions(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/completions").await
+}
+
+#[post("/embeddings")]
+async fn embeddings(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/embeddings").await
+}
+
+#[post("/rerank")]
+async fn rerank(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/rerank").await
+}
+
+#[post("/images/generations")]
+async fn images_generations(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/images/generations").await
+}
+
+#[post("/images/edits")]
+async fn images_edits(req: HttpRequest, body: Bytes) -> impl Responder {
+    forward_request(req, body, "/v1/images/edit").await
+}
+
+/// Audio is uploaded by multipart form, so model is read from form field and body is forwarded as it is
+#[post("/audio/transcriptions")]
+async fn audio_transcriptions(req: HttpRequest, body: Bytes) -> impl Responder {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+    let Some(model) = gateway_service::get_multipart_field(content_type.as_str(), &body, "model")
+    else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "invalid_request_error",
+            "Model is required".to_string(),
+        );
+    };
+    forward_model_request(req, body, common::WHISPER_INFERENCE_PATH, model, content_type).await
+}
+
+#[get("/models")]
+async fn get_models() -> impl Responder {
+    let model_servers = gateway_service::get_ready_model_servers();
+    let mut models: Vec<GatewayModelData> = vec![];
+    model_servers.iter().for_each(|model_server| {
+        let exists = models.iter().any(|model| model.id == model_server.model_name);
+        if !exists {
+            models.push(GatewayModelData {
+                id: model_server.model_name.clone(),
+                object: "model".to_string(),
+                created: SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs(),
+                owned_by: "synvek".to_string(),
+            });
+        }
+    });
+    let response = GatewayModelsResponse {
+        object: "list".to_string(),
+        data: models,
+    };
+    HttpResponse::Ok().json(response)
+}
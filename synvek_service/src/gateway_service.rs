@@ -0,0 +1,291 @@
+use crate::concurrency_service::{ConcurrencyError, ConcurrencyLimit, ConcurrencyPermit};
+use crate::model_service::ModelInfo;
+use crate::{common, concurrency_service, config, model_service, process_service};
+use reqwest::Client;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Round-robin position of each replica group
+static ROUND_ROBIN_POSITIONS: OnceLock<Arc<Mutex<HashMap<String, usize>>>> = OnceLock::new();
+
+/// Locks of on demand starts, keyed by model name, so concurrent requests share one start
+static ON_DEMAND_START_LOCKS: OnceLock<Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>> =
+    OnceLock::new();
+
+fn init_round_robin_positions() -> Arc<Mutex<HashMap<String, usize>>> {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn init_on_demand_start_locks() -> Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+fn get_on_demand_start_lock(model_name: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let start_locks = ON_DEMAND_START_LOCKS.get_or_init(|| init_on_demand_start_locks());
+    let mut start_locks = start_locks.lock().unwrap();
+    start_locks
+        .entry(model_name.to_string())
+        .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(())))
+        .clone()
+}
+
+/// Find running model server by model name first and then by model id. Request is balanced across
+/// ready replicas of one replica group of model.
+pub fn find_model_server(model: &str) -> Option<ModelInfo> {
+    let model_servers = model_service::get_model_servers();
+    let mut replicas: Vec<ModelInfo> = model_servers
+        .iter()
+        .filter(|model_server| model_server.started && model_server.model_name == model)
+        .cloned()
+        .collect();
+    if replicas.is_empty() {
+        replicas = model_servers
+            .iter()
+            .filter(|model_server| model_server.started && model_server.model_id == model)
+            .cloned()
+            .collect();
+    }
+    select_replica(replicas)
+}
+
+/// Group ready model servers by replica group, model server started alone is a group by itself.
+/// Group with most ready replicas serves request and its replicas are sorted by task id so
+/// round-robin order is stable.
+fn group_replicas(replicas: Vec<ModelInfo>) -> Option<(String, Vec<ModelInfo>)> {
+    let mut groups: HashMap<String, Vec<ModelInfo>> = HashMap::new();
+    for replica in replicas {
+        let replica_group = replica
+            .replica_group
+            .clone()
+            .unwrap_or(replica.task_id.clone());
+        groups.entry(replica_group).or_default().push(replica);
+    }
+    let (replica_group, mut replicas) = groups
+        .into_iter()
+        .max_by(|(a_group, a), (b_group, b)| a.len().cmp(&b.len()).then(b_group.cmp(a_group)))?;
+    replicas.sort_by(|a, b| a.task_id.cmp(&b.task_id));
+    Some((replica_group, replicas))
+}
+
+/// Select replica with load balancing of its replica group
+fn select_replica(replicas: Vec<ModelInfo>) -> Option<ModelInfo> {
+    let (replica_group, mut replicas) = group_replicas(replicas)?;
+    if replicas.len() <= 1 {
+        return replicas.pop();
+    }
+    let load_balancing = replicas[0].load_balancing.clone();
+    let positions = ROUND_ROBIN_POSITIONS.get_or_init(|| init_round_robin_positions());
+    let mut positions = positions.lock().unwrap();
+    let position = positions.entry(replica_group).or_insert(0);
+    let start = *position % replicas.len();
+    *position = position.wrapping_add(1);
+    let index = if load_balancing == common::LOAD_BALANCING_LEAST_BUSY {
+        // Ties are broken by round-robin position
+        (0..replicas.len())
+            .map(|offset| (start + offset) % replicas.len())
+            .min_by_key(|index| replicas[*index].active_requests)
+            .unwrap_or(start)
+    } else {
+        start
+    };
+    Some(replicas.swap_remove(index))
+}
+
+/// Find running model server, wait for it if it is still loading or start it on demand if it is
+/// unloaded by idle timeout.
+pub async fn resolve_model_server(model: &str) -> Option<ModelInfo> {
+    let model_server = find_model_server(model);
+    if model_server.is_some() {
+        return model_server;
+    }
+    if !has_model_server(model) && !start_unloaded_model_server(model).await {
+        return None;
+    }
+    let model_server = wait_for_model_server(model).await;
+    if let Some(model_server) = model_server.as_ref() {
+        model_service::remove_unloaded_model_server(model_server.model_name.as_str());
+    }
+    model_server
+}
+
+/// Start model server unloaded by idle timeout. Concurrent requests wait for the same start until
+/// started model server is registered. Unloaded model server is kept until it is ready, so a failed
+/// start can be tried again by next request.
+async fn start_unloaded_model_server(model: &str) -> bool {
+    let Some(args) = model_service::get_unloaded_model_server(model) else {
+        return false;
+    };
+    let start_lock = get_on_demand_start_lock(args.model_name.as_str());
+    let _start_guard = start_lock.lock().await;
+    // Model server is started by another request while waiting
+    if has_model_server(model) {
+        return true;
+    }
+    let Some(args) = model_service::get_unloaded_model_server(model) else {
+        return false;
+    };
+    tracing::info!("Starting model server {} on demand", model);
+    let multi_process = config::get_synvek_config().multi_process;
+    let start_result = model_service::start_model_server_from_web(multi_process, &args).await;
+    if let Err(e) = start_result {
+        tracing::error!("Failed to start model server {} on demand with error: {}", model, e);
+        return false;
+    }
+    // Model server is registered from process thread, so wait for it before other requests look it up
+    let start_time = SystemTime::now();
+    while start_time.elapsed().unwrap_or_default()
+        < Duration::from_secs(common::ON_DEMAND_REGISTER_TIMEOUT_SECS)
+    {
+        if has_model_server(model) {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    tracing::error!("Model server {} started on demand is never registered", model);
+    false
+}
+
+fn has_model_server(model: &str) -> bool {
+    model_service::get_model_servers()
+        .iter()
+        .any(|model_server| model_server.model_name == model || model_server.model_id == model)
+}
+
+async fn wait_for_model_server(model: &str) -> Option<ModelInfo> {
+    let start_time = SystemTime::now();
+    while start_time.elapsed().unwrap_or_default()
+        < Duration::from_secs(common::ON_DEMAND_START_TIMEOUT_SECS)
+    {
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        let model_server = find_model_server(model);
+        if model_server.is_some() {
+            return model_server;
+        }
+        // Model server failed to start
+        if !has_model_server(model) {
+            return None;
+        }
+    }
+    None
+}
+
+/// Record request activity on model server, it keeps model server from being unloaded by idle timeout
+pub fn touch_model_server(model_server: &ModelInfo) {
+    process_service::touch_process(model_server.task_id.as_str());
+}
+
+/// Wait for free slot on model server if it has concurrency limit, permit is none if requests of
+/// model server are not limited.
+pub async fn acquire_model_server(
+    model_server: &ModelInfo,
+) -> Result<Option<ConcurrencyPermit>, ConcurrencyError> {
+    // Sd server running in this process takes permit of the same limiter by itself
+    if model_server.capability == common::CAPABILITY_IMAGE
+        && process_service::is_in_process_server(model_server.task_id.as_str())
+    {
+        return Ok(None);
+    }
+    let Some(limit) = ConcurrencyLimit::create(
+        model_server.max_concurrency,
+        model_server.max_queue_size,
+        model_server.queue_timeout,
+    ) else {
+        return Ok(None);
+    };
+    let limiter = concurrency_service::get_limiter(model_server.task_id.as_str(), limit);
+    limiter.acquire().await.map(Some)
+}
+
+/// Request in flight on model server, it is counted until guard is dropped
+pub struct ModelRequestGuard {
+    task_id: String,
+}
+
+impl ModelRequestGuard {
+    pub fn new(model_server: &ModelInfo) -> Self {
+        process_service::begin_process_request(model_server.task_id.as_str());
+        ModelRequestGuard {
+            task_id: model_server.task_id.clone(),
+        }
+    }
+}
+
+impl Drop for ModelRequestGuard {
+    fn drop(&mut self) {
+        process_service::end_process_request(self.task_id.as_str());
+    }
+}
+
+/// Get running model servers which are ready to serve
+pub fn get_ready_model_servers() -> Vec<ModelInfo> {
+    model_service::get_model_servers()
+        .into_iter()
+        .filter(|model_server| model_server.started)
+        .collect()
+}
+
+pub fn get_model_server_url(model_server: &ModelInfo, path: &str) -> String {
+    let mut model_server_address = "http://127.0.0.1:".to_string();
+    model_server_address.push_str(model_server.port.as_str());
+    model_server_address.push_str(path);
+    model_server_address
+}
+
+/// Read text field from multipart form body
+pub fn get_multipart_field(content_type: &str, body: &[u8], name: &str) -> Option<String> {
+    let boundary = content_type
+        .split(';')
+        .find_map(|param| param.trim().strip_prefix("boundary="))?
+        .trim_matches('"');
+    let delimiter = format!("--{}", boundary);
+    let field_header = format!("name=\"{}\"", name);
+    let mut parts = split_bytes(body, delimiter.as_bytes());
+    parts.find_map(|part| {
+        let header_end = find_bytes(part, b"\r\n\r\n")?;
+        let header = String::from_utf8_lossy(&part[..header_end]);
+        let disposition = header
+            .lines()
+            .find(|line| line.to_lowercase().starts_with("content-disposition"))?;
+        // File fields are skipped even if they have same name
+        if !disposition.contains(field_header.as_str()) || disposition.contains("filename=") {
+            return None;
+        }
+        let value = &part[header_end + 4..];
+        let value = value.strip_suffix(b"\r\n").unwrap_or(value);
+        Some(String::from_utf8_lossy(value).trim().to_string())
+    })
+}
+
+fn find_bytes(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len()).position(|window| window == pattern)
+}
+
+fn split_bytes<'a>(data: &'a [u8], delimiter: &'a [u8]) -> impl Iterator<Item = &'a [u8]> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        match find_bytes(rest, delimiter) {
+            Some(position) => {
+                let part = &rest[..position];
+                rest = &rest[position + delimiter.len()..];
+                Some(part)
+            }
+            None => {
+                let part = rest;
+                rest = &[];
+                Some(part)
+            }
+        }
+    })
+}
+
+/// No request timeout here since streaming completions may last for a long time
+pub fn create_gateway_client() -> anyhow::Result<Client> {
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(5))
+        .build()?;
+    Ok(client)
+}
@@ -0,0 +1,380 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Strings and arrays longer than this are treated as corrupted file
+const GGUF_MAX_STRING_LENGTH: u64 = 64 * 1024 * 1024;
+
+const GGUF_TYPE_UINT8: u32 = 0;
+const GGUF_TYPE_INT8: u32 = 1;
+const GGUF_TYPE_UINT16: u32 = 2;
+const GGUF_TYPE_INT16: u32 = 3;
+const GGUF_TYPE_UINT32: u32 = 4;
+const GGUF_TYPE_INT32: u32 = 5;
+const GGUF_TYPE_FLOAT32: u32 = 6;
+const GGUF_TYPE_BOOL: u32 = 7;
+const GGUF_TYPE_STRING: u32 = 8;
+const GGUF_TYPE_ARRAY: u32 = 9;
+const GGUF_TYPE_UINT64: u32 = 10;
+const GGUF_TYPE_INT64: u32 = 11;
+const GGUF_TYPE_FLOAT64: u32 = 12;
+
+/// Model facts read from GGUF header
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GgufInfo {
+    pub version: u32,
+
+    pub name: Option<String>,
+
+    pub architecture: Option<String>,
+
+    /// Total elements of all tensors
+    pub parameter_count: u64,
+
+    pub quantization_type: Option<String>,
+
+    /// Context length which model is trained with
+    pub context_length: Option<u64>,
+
+    pub tensor_count: u64,
+
+    pub block_count: Option<u64>,
+
+    pub embedding_length: Option<u64>,
+
+    pub head_count: Option<u64>,
+
+    pub head_count_kv: Option<u64>,
+
+    pub chat_template: Option<String>,
+
+    /// Scalar and string metadata, arrays like tokenizer vocabulary are skipped
+    #[serde(skip)]
+    pub metadata: HashMap<String, Value>,
+}
+
+struct GgufReader<R: Read> {
+    reader: R,
+    version: u32,
+}
+
+impl<R: Read> GgufReader<R> {
+    fn read_bytes<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        let mut buf = [0u8; N];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes::<4>()?))
+    }
+
+    fn read_u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_le_bytes(self.read_bytes::<8>()?))
+    }
+
+    /// Counts and lengths are 32 bits in GGUF version 1
+    fn read_count(&mut self) -> anyhow::Result<u64> {
+        if self.version == 1 {
+            Ok(self.read_u32()? as u64)
+        } else {
+            self.read_u64()
+        }
+    }
+
+    fn read_string(&mut self) -> anyhow::Result<String> {
+        let length = self.read_count()?;
+        if length > GGUF_MAX_STRING_LENGTH {
+            return Err(anyhow::anyhow!("Invalid string length: {}", length));
+        }
+        let mut buf = vec![0u8; length as usize];
+        self.reader.read_exact(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).to_string())
+    }
+
+    fn skip(&mut self, length: u64) -> anyhow::Result<()> {
+        let skipped = std::io::copy(&mut (&mut self.reader).take(length), &mut std::io::sink())?;
+        if skipped != length {
+            return Err(anyhow::anyhow!("Unexpected end of file"));
+        }
+        Ok(())
+    }
+
+    fn read_value(&mut self, value_type: u32) -> anyhow::Result<Option<Value>> {
+        let value = match value_type {
+            GGUF_TYPE_UINT8 => Value::from(self.read_bytes::<1>()?[0]),
+            GGUF_TYPE_INT8 => Value::from(self.read_bytes::<1>()?[0] as i8),
+            GGUF_TYPE_UINT16 => Value::from(u16::from_le_bytes(self.read_bytes::<2>()?)),
+            GGUF_TYPE_INT16 => Value::from(i16::from_le_bytes(self.read_bytes::<2>()?)),
+            GGUF_TYPE_UINT32 => Value::from(self.read_u32()?),
+            GGUF_TYPE_INT32 => Value::from(i32::from_le_bytes(self.read_bytes::<4>()?)),
+            GGUF_TYPE_FLOAT32 => Value::from(f32::from_le_bytes(self.read_bytes::<4>()?)),
+            GGUF_TYPE_BOOL => Value::from(self.read_bytes::<1>()?[0] != 0),
+            GGUF_TYPE_STRING => Value::from(self.read_string()?),
+            GGUF_TYPE_UINT64 => Value::from(self.read_u64()?),
+            GGUF_TYPE_INT64 => Value::from(i64::from_le_bytes(self.read_bytes::<8>()?)),
+            GGUF_TYPE_FLOAT64 => Value::from(f64::from_le_bytes(self.read_bytes::<8>()?)),
+            GGUF_TYPE_ARRAY => {
+                self.skip_array()?;
+                return Ok(None);
+            }
+            _ => return Err(anyhow::anyhow!("Unknown value type: {}", value_type)),
+        };
+        Ok(Some(value))
+    }
+
+    /// Arrays are skipped since they are tokenizer data which may have hundreds of thousands items
+    fn skip_array(&mut self) -> anyhow::Result<()> {
+        let item_type = self.read_u32()?;
+        let length = self.read_count()?;
+        let item_size: u64 = match item_type {
+            GGUF_TYPE_UINT8 | GGUF_TYPE_INT8 | GGUF_TYPE_BOOL => 1,
+            GGUF_TYPE_UINT16 | GGUF_TYPE_INT16 => 2,
+            GGUF_TYPE_UINT32 | GGUF_TYPE_INT32 | GGUF_TYPE_FLOAT32 => 4,
+            GGUF_TYPE_UINT64 | GGUF_TYPE_INT64 | GGUF_TYPE_FLOAT64 => 8,
+            _ => 0,
+        };
+        if item_size > 0 {
+            return self.skip(length.saturating_mul(item_size));
+        }
+        for _ in 0..length {
+            match item_type {
+                GGUF_TYPE_STRING => {
+                    let string_length = self.read_count()?;
+                    self.skip(string_length)?;
+                }
+                GGUF_TYPE_ARRAY => self.skip_array()?,
+                _ => return Err(anyhow::anyhow!("Unknown array type: {}", item_type)),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read model facts from header of GGUF file, tensor data is not read.
+pub fn read_gguf_info<P: AsRef<Path>>(path: P) -> anyhow::Result<GgufInfo> {
+    let file = File::open(path.as_ref()).map_err(|e| {
+        anyhow::anyhow!("Failed to open GGUF file: {} with error: {}", path.as_ref().display(), e)
+    })?;
+    parse_gguf(BufReader::new(file))
+}
+
+pub fn parse_gguf<R: Read>(reader: R) -> anyhow::Result<GgufInfo> {
+    let mut reader = GgufReader { reader, version: 0 };
+    let magic = reader.read_bytes::<4>()?;
+    if &magic != GGUF_MAGIC {
+        return Err(anyhow::anyhow!("Invalid GGUF file"));
+    }
+    reader.version = reader.read_u32()?;
+    if reader.version == 0 || reader.version > 3 {
+        return Err(anyhow::anyhow!("Unsupported GGUF version: {}", reader.version));
+    }
+    let tensor_count = reader.read_count()?;
+    let metadata_count = reader.read_count()?;
+    let mut metadata: HashMap<String, Value> = HashMap::new();
+    for _ in 0..metadata_count {
+        let key = reader.read_string()?;
+        let value_type = reader.read_u32()?;
+        if let Some(value) = reader.read_value(value_type)? {
+            metadata.insert(key, value);
+        }
+    }
+    // Quantization type is guessed by tensor type holding most elements if file type is missing
+    let mut parameter_count: u64 = 0;
+    let mut tensor_type_counts: HashMap<u32, u64> = HashMap::new();
+    for _ in 0..tensor_count {
+        let _name = reader.read_string()?;
+        let dimension_count = reader.read_u32()?;
+        let mut element_count: u64 = 1;
+        for _ in 0..dimension_count {
+            element_count = element_count.saturating_mul(reader.read_count()?);
+        }
+        let tensor_type = reader.read_u32()?;
+        let _offset = reader.read_u64()?;
+        parameter_count = parameter_count.saturating_add(element_count);
+        *tensor_type_counts.entry(tensor_type).or_insert(0) += element_count;
+    }
+
+    let get_string = |key: &str| metadata.get(key).and_then(|value| value.as_str()).map(|value| value.to_string());
+    let architecture = get_string("general.architecture");
+    let get_architecture_u64 = |key: &str| {
+        architecture.as_ref().and_then(|architecture| {
+            metadata
+                .get(format!("{}.{}", architecture, key).as_str())
+                .and_then(|value| value.as_u64())
+        })
+    };
+    let quantization_type = metadata
+        .get("general.file_type")
+        .and_then(|value| value.as_u64())
+        .and_then(|file_type| get_file_type_name(file_type as u32))
+        .or_else(|| {
+            tensor_type_counts
+                .iter()
+                .max_by_key(|(_, count)| **count)
+                .and_then(|(tensor_type, _)| get_tensor_type_name(*tensor_type))
+        })
+        .map(|name| name.to_string());
+    Ok(GgufInfo {
+        version: reader.version,
+        name: get_string("general.name"),
+        architecture: architecture.clone(),
+        parameter_count,
+        quantization_type,
+        context_length: get_architecture_u64("context_length"),
+        tensor_count,
+        block_count: get_architecture_u64("block_count"),
+        embedding_length: get_architecture_u64("embedding_length"),
+        head_count: get_architecture_u64("attention.head_count"),
+        head_count_kv: get_architecture_u64("attention.head_count_kv"),
+        chat_template: get_string("tokenizer.chat_template"),
+        metadata,
+    })
+}
+
+/// Names of llama_ftype in llama.cpp
+fn get_file_type_name(file_type: u32) -> Option<&'static str> {
+    let name = match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        36 => "TQ1_0",
+        37 => "TQ2_0",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Names of ggml_type in ggml
+fn get_tensor_type_name(tensor_type: u32) -> Option<&'static str> {
+    let name = match tensor_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        6 => "Q5_0",
+        7 => "Q5_1",
+        8 => "Q8_0",
+        9 => "Q8_1",
+        10 => "Q2_K",
+        11 => "Q3_K",
+        12 => "Q4_K",
+        13 => "Q5_K",
+        14 => "Q6_K",
+        15 => "Q8_K",
+        16 => "IQ2_XXS",
+        17 => "IQ2_XS",
+        18 => "IQ3_XXS",
+        19 => "IQ1_S",
+        20 => "IQ4_NL",
+        21 => "IQ3_S",
+        22 => "IQ2_S",
+        23 => "IQ4_XS",
+        29 => "IQ1_M",
+        30 => "BF16",
+        34 => "TQ1_0",
+        35 => "TQ2_0",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_string(data: &mut Vec<u8>, value: &str) {
+        data.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        data.extend_from_slice(value.as_bytes());
+    }
+
+    fn build_gguf() -> Vec<u8> {
+        let mut data: Vec<u8> = vec![];
+        data.extend_from_slice(GGUF_MAGIC);
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&2u64.to_le_bytes());
+        data.extend_from_slice(&5u64.to_le_bytes());
+        push_string(&mut data, "general.architecture");
+        data.extend_from_slice(&GGUF_TYPE_STRING.to_le_bytes());
+        push_string(&mut data, "llama");
+        push_string(&mut data, "general.file_type");
+        data.extend_from_slice(&GGUF_TYPE_UINT32.to_le_bytes());
+        data.extend_from_slice(&15u32.to_le_bytes());
+        push_string(&mut data, "llama.context_length");
+        data.extend_from_slice(&GGUF_TYPE_UINT32.to_le_bytes());
+        data.extend_from_slice(&4096u32.to_le_bytes());
+        push_string(&mut data, "tokenizer.ggml.tokens");
+        data.extend_from_slice(&GGUF_TYPE_ARRAY.to_le_bytes());
+        data.extend_from_slice(&GGUF_TYPE_STRING.to_le_bytes());
+        data.extend_from_slice(&2u64.to_le_bytes());
+        push_string(&mut data, "<s>");
+        push_string(&mut data, "</s>");
+        push_string(&mut data, "tokenizer.chat_template");
+        data.extend_from_slice(&GGUF_TYPE_STRING.to_le_bytes());
+        push_string(&mut data, "{{ messages }}");
+        for (name, dimensions) in [("token_embd.weight", vec![64u64, 32]), ("output_norm.weight", vec![64u64])] {
+            push_string(&mut data, name);
+            data.extend_from_slice(&(dimensions.len() as u32).to_le_bytes());
+            for dimension in dimensions {
+                data.extend_from_slice(&dimension.to_le_bytes());
+            }
+            data.extend_from_slice(&12u32.to_le_bytes());
+            data.extend_from_slice(&0u64.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_parse_gguf() {
+        let info = parse_gguf(build_gguf().as_slice()).unwrap();
+        assert_eq!(info.version, 3);
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+        assert_eq!(info.quantization_type.as_deref(), Some("Q4_K_M"));
+        assert_eq!(info.context_length, Some(4096));
+        assert_eq!(info.tensor_count, 2);
+        assert_eq!(info.parameter_count, 64 * 32 + 64);
+        assert_eq!(info.chat_template.as_deref(), Some("{{ messages }}"));
+        assert!(!info.metadata.contains_key("tokenizer.ggml.tokens"));
+    }
+
+    #[test]
+    fn test_parse_invalid_gguf() {
+        let mut data = build_gguf();
+        data[0] = b'X';
+        assert!(parse_gguf(data.as_slice()).is_err());
+        let data = build_gguf();
+        assert!(parse_gguf(&data[..data.len() - 4]).is_err());
+    }
+}
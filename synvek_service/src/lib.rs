@@ -24,6 +24,19 @@ pub mod sd_service;
 pub mod sd_api;
 pub mod sd_server;
 pub mod modelscope_helper;
+pub mod gateway_service;
+pub mod gateway_api;
+pub mod model_log_service;
+pub mod resource_service;
+pub mod gguf_helper;
+pub mod memory_service;
+pub mod backend_service;
+pub mod mock_server;
+pub mod profile_service;
+pub mod concurrency_service;
+pub mod template_service;
+pub mod download_helper;
+pub mod download_service;
 
 use std::ffi::OsString;
 /// 导出所有公共接口
@@ -26,6 +26,19 @@ mod sd_service;
 mod sd_api;
 mod sd_server;
 mod modelscope_helper;
+mod gateway_service;
+mod gateway_api;
+mod model_log_service;
+mod resource_service;
+mod gguf_helper;
+mod memory_service;
+mod backend_service;
+mod mock_server;
+mod profile_service;
+mod concurrency_service;
+mod template_service;
+mod download_helper;
+mod download_service;
 
 use tracing_subscriber::fmt::writer::MakeWriterExt;
 use tracing_subscriber::layer::SubscriberExt;
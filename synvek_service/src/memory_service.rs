@@ -0,0 +1,168 @@
+use crate::config::Config;
+use crate::fetch_service::Task;
+use crate::gguf_helper::GgufInfo;
+use crate::model_service::ModelServiceArgs;
+use crate::{backend_service, common, fetch_service, gguf_helper, model_service};
+use serde::Serialize;
+use std::fs;
+
+/// Estimated memory of model server in bytes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryEstimate {
+    /// Size of model weights
+    pub weights_size: u64,
+
+    /// KV cache for requested context length, it is 0 if model metadata is not available
+    pub kv_cache_size: u64,
+
+    /// Runtime and compute buffers
+    pub overhead_size: u64,
+
+    /// Memory required in system RAM
+    pub ram_size: u64,
+
+    /// Memory required in GPU, it can't be checked since free VRAM is not known
+    pub vram_size: u64,
+
+    /// Available system memory, none if it is not known on this platform
+    pub available_size: Option<u64>,
+
+    pub warning: Option<String>,
+}
+
+/// Estimate memory of model server and compare it with available system memory. Error is returned
+/// if model doesn't fit into available memory.
+pub fn check_model_memory(args: &ModelServiceArgs) -> anyhow::Result<MemoryEstimate> {
+    let requires_model = backend_service::get_backend(args.backend.as_str())
+        .map(|backend| backend.requires_model())
+        .unwrap_or(true);
+    if !requires_model {
+        return Ok(MemoryEstimate::default());
+    }
+    let Some(task) = fetch_service::load_local_task(args.model_name.as_str()) else {
+        return Err(anyhow::anyhow!("Task not found"));
+    };
+    let mut estimate = estimate_model_memory(&task, args);
+    estimate.available_size = get_available_memory();
+    if let Some(available_size) = estimate.available_size {
+        if estimate.ram_size > available_size {
+            return Err(anyhow::anyhow!(
+                "Not enough memory to start model: {} MB required but {} MB available",
+                estimate.ram_size / 1024 / 1024,
+                available_size / 1024 / 1024
+            ));
+        }
+        if estimate.ram_size as f64 > available_size as f64 * common::MEMORY_WARNING_RATIO {
+            estimate.warning = Some(format!(
+                "Model may run out of memory: {} MB required and {} MB available",
+                estimate.ram_size / 1024 / 1024,
+                available_size / 1024 / 1024
+            ));
+        }
+    }
+    Ok(estimate)
+}
+
+/// Weights are sized by downloaded files, KV cache by GGUF metadata and requested context length.
+/// Layers offloaded by gpu_layers are counted in VRAM, all layers are offloaded if it is not set.
+pub fn estimate_model_memory(task: &Task, args: &ModelServiceArgs) -> MemoryEstimate {
+    let weights_size = get_weights_size(task);
+    let gguf_info = model_service::get_task_gguf_file(task)
+        .and_then(|gguf_file| gguf_helper::read_gguf_info(gguf_file).ok());
+    // Backends launch with default context length rather than trained one if it is not requested
+    let context_length = args
+        .context_length
+        .map(|context_length| context_length as u64)
+        .unwrap_or_else(|| {
+            gguf_info
+                .as_ref()
+                .and_then(|gguf_info| gguf_info.context_length)
+                .unwrap_or(common::MEMORY_DEFAULT_CONTEXT_LENGTH)
+                .min(common::MEMORY_DEFAULT_CONTEXT_LENGTH)
+        });
+    let kv_cache_size = gguf_info
+        .as_ref()
+        .map(|gguf_info| get_kv_cache_size(gguf_info, context_length))
+        .unwrap_or(0);
+    let batch_size = args
+        .batch_size
+        .map(|batch_size| batch_size as u64)
+        .unwrap_or(common::MEMORY_DEFAULT_BATCH_SIZE);
+    let overhead_size =
+        common::MEMORY_OVERHEAD_SIZE + batch_size.saturating_mul(common::MEMORY_BATCH_TOKEN_SIZE);
+
+    let gpu_ratio = get_gpu_ratio(args, gguf_info.as_ref());
+    let vram_size = ((weights_size + kv_cache_size) as f64 * gpu_ratio) as u64;
+    let ram_size = weights_size + kv_cache_size + overhead_size - vram_size;
+    MemoryEstimate {
+        weights_size,
+        kv_cache_size,
+        overhead_size,
+        ram_size,
+        vram_size,
+        available_size: None,
+        warning: None,
+    }
+}
+
+fn get_weights_size(task: &Task) -> u64 {
+    if task.private_model {
+        let config = Config::new();
+        let mut model_path = config.get_model_dir();
+        model_path.push(task.task_name.clone());
+        return fs::metadata(model_path)
+            .map(|metadata| metadata.len())
+            .unwrap_or(0);
+    }
+    task.task_items.iter().map(|item| item.file_size).sum()
+}
+
+/// K and V of each layer are stored in f16
+fn get_kv_cache_size(gguf_info: &GgufInfo, context_length: u64) -> u64 {
+    let (Some(block_count), Some(embedding_length)) =
+        (gguf_info.block_count, gguf_info.embedding_length)
+    else {
+        return 0;
+    };
+    let head_count = gguf_info.head_count.unwrap_or(1).max(1);
+    let head_count_kv = gguf_info.head_count_kv.unwrap_or(head_count);
+    let embedding_length_kv = embedding_length * head_count_kv / head_count;
+    2 * block_count * context_length * embedding_length_kv * 2
+}
+
+/// Share of weights and KV cache placed on GPU. Memory is shared with GPU on macOS so all of it is
+/// counted in RAM there.
+fn get_gpu_ratio(args: &ModelServiceArgs, gguf_info: Option<&GgufInfo>) -> f64 {
+    if args.cpu || args.acceleration == common::ACCELERATION_CPU || cfg!(target_os = "macos") {
+        return 0.0;
+    }
+    let Some(gpu_layers) = args.gpu_layers else {
+        return 1.0;
+    };
+    match gguf_info.and_then(|gguf_info| gguf_info.block_count) {
+        Some(block_count) if block_count > 0 => (gpu_layers as f64 / block_count as f64).min(1.0),
+        _ => {
+            if gpu_layers > 0 {
+                1.0
+            } else {
+                0.0
+            }
+        }
+    }
+}
+
+/// Available memory from /proc/meminfo, it includes reclaimable cache
+#[cfg(target_os = "linux")]
+pub fn get_available_memory() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines().find_map(|line| {
+        let value = line.strip_prefix("MemAvailable:")?;
+        let size = value.trim().trim_end_matches("kB").trim().parse::<u64>().ok()?;
+        Some(size * 1024)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get_available_memory() -> Option<u64> {
+    None
+}
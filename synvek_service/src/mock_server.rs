@@ -0,0 +1,208 @@
+use crate::common;
+use crate::config;
+use actix_web::http::header;
+use actix_web::middleware::Logger;
+use actix_web::{App, HttpResponse, HttpServer, Responder, web};
+use actix_web::{get, post};
+use base64::{Engine as _, engine::general_purpose};
+use image::{ImageFormat, Rgb, RgbImage};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Request for Mock Chat Completions, other fields of request are ignored
+#[derive(Debug, Deserialize)]
+pub struct MockChatCompletionRequest {
+    #[serde(default)]
+    pub model: String,
+
+    #[serde(default)]
+    pub stream: bool,
+}
+
+/// Request for Mock Image Generation, other fields of request are ignored
+#[derive(Debug, Deserialize)]
+pub struct MockImageGenerationRequest {
+    #[serde(default)]
+    pub n: Option<usize>,
+
+    #[serde(default)]
+    pub width: Option<usize>,
+
+    #[serde(default)]
+    pub height: Option<usize>,
+}
+
+#[get("/health")]
+async fn health() -> impl Responder {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+#[post("/chat/completions")]
+async fn chat_completions(req: web::Json<MockChatCompletionRequest>) -> impl Responder {
+    let created = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4());
+    if req.stream {
+        let mut body = String::new();
+        let deltas = [
+            json!({ "role": "assistant", "content": "" }),
+            json!({ "content": common::MOCK_CHAT_CONTENT }),
+        ];
+        deltas.iter().for_each(|delta| {
+            let chunk = json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": req.model,
+                "choices": [{ "index": 0, "delta": delta, "finish_reason": null }],
+            });
+            body.push_str(format!("data: {}\n\n", chunk).as_str());
+        });
+        let chunk = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": created,
+            "model": req.model,
+            "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }],
+        });
+        body.push_str(format!("data: {}\n\n", chunk).as_str());
+        body.push_str("data: [DONE]\n\n");
+        return HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+            .body(body);
+    }
+    HttpResponse::Ok().json(json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": req.model,
+        "choices": [{
+            "index": 0,
+            "message": { "role": "assistant", "content": common::MOCK_CHAT_CONTENT },
+            "finish_reason": "stop",
+        }],
+        "usage": { "prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0 },
+    }))
+}
+
+/// Same response as sd server with solid color PNG images
+#[post("/images/generations")]
+async fn generate_image(req: web::Json<MockImageGenerationRequest>) -> impl Responder {
+    image_response(&req)
+}
+
+#[post("/images/edit")]
+async fn edit_image(req: web::Json<MockImageGenerationRequest>) -> impl Responder {
+    image_response(&req)
+}
+
+fn image_response(req: &MockImageGenerationRequest) -> HttpResponse {
+    let count = req.n.unwrap_or(1).max(1);
+    let width = req.width.unwrap_or(common::MOCK_IMAGE_SIZE).clamp(1, 4096) as u32;
+    let height = req.height.unwrap_or(common::MOCK_IMAGE_SIZE).clamp(1, 4096) as u32;
+    match create_image(width, height) {
+        Ok(image_data) => {
+            let data: Vec<_> = (0..count)
+                .map(|_| json!({ "url": null, "b64_json": image_data }))
+                .collect();
+            HttpResponse::Ok().json(json!({
+                "success": true,
+                "code": "",
+                "message": "",
+                "data": data,
+            }))
+        }
+        Err(e) => HttpResponse::Ok().json(json!({
+            "success": false,
+            "code": "",
+            "message": e.to_string(),
+            "data": [],
+        })),
+    }
+}
+
+fn create_image(width: u32, height: u32) -> anyhow::Result<String> {
+    let image = RgbImage::from_pixel(width, height, Rgb([128, 128, 128]));
+    let mut image_data = Cursor::new(Vec::new());
+    image.write_to(&mut image_data, ImageFormat::Png)?;
+    let base64_string = general_purpose::STANDARD.encode(image_data.into_inner());
+    Ok(format!("data:image/png;base64,{}", base64_string))
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(health).service(
+        web::scope("/v1")
+            .service(chat_completions)
+            .service(generate_image)
+            .service(edit_image),
+    );
+}
+
+/// Start mock model server, it serves canned responses without native backend library so model
+/// server flows can be tested anywhere.
+pub async fn start_mock_server(port: &str) -> anyhow::Result<()> {
+    let config = config::get_synvek_config();
+    let host = config.host;
+    let port = port.parse::<u16>()?;
+    tracing::info!("Starting mock server on host:{} and port:{}", host, port);
+    let http_server = HttpServer::new(|| {
+        App::new()
+            .wrap(Logger::default())
+            .configure(configure_routes)
+    })
+    .bind((host, port))?
+    .run();
+    http_server.await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::test;
+
+    #[actix_web::test]
+    async fn test_health() {
+        let app = test::init_service(App::new().configure(configure_routes)).await;
+        let req = test::TestRequest::get().uri("/health").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert!(resp.status().is_success());
+    }
+
+    #[actix_web::test]
+    async fn test_chat_completions() {
+        let app = test::init_service(App::new().configure(configure_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/v1/chat/completions")
+            .set_json(json!({ "model": "mock-model", "messages": [] }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["model"], "mock-model");
+        assert_eq!(
+            resp["choices"][0]["message"]["content"],
+            common::MOCK_CHAT_CONTENT
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_generate_image() {
+        let app = test::init_service(App::new().configure(configure_routes)).await;
+        let req = test::TestRequest::post()
+            .uri("/v1/images/generations")
+            .set_json(json!({ "n": 2, "width": 8, "height": 8 }))
+            .to_request();
+        let resp: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(resp["success"], true);
+        assert_eq!(resp["data"].as_array().map(|data| data.len()), Some(2));
+        assert!(
+            resp["data"][0]["b64_json"]
+                .as_str()
+                .unwrap()
+                .starts_with("data:image/png;base64,")
+        );
+    }
+}
@@ -9,11 +9,19 @@ use actix_web::{get, post, HttpRequest};
 use serde::{Deserialize, Serialize};
 use base64::{Engine as _, engine::general_purpose};
 use crate::fetch_service;
+use crate::profile_service::{LaunchProfile, TaskProfiles};
+use crate::template_service::ChatTemplateInfo;
 use crate::fetch_service::{Task, TaskItem, RunningTask};
-use crate::model_service::{ModelInfo, ModelServiceArgs};
+use crate::model_service::{LoraAdapter, ModelInfo, ModelServiceArgs};
 use crate::common::ServiceRef;
-use crate::config;
-use crate::model_service;
+use crate::gguf_helper::GgufInfo;
+use crate::memory_service::MemoryEstimate;
+use crate::process_service::{ProcessState, ProcessStateTransition};
+use crate::{common, config};
+use crate::{memory_service, model_log_service, model_service, profile_service, template_service};
+use actix_web::web::Bytes;
+use async_stream::stream;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ModelServerData {
@@ -23,6 +31,18 @@ pub struct ModelServerData {
     /// Started
     pub started: bool,
 
+    /// Lifecycle state
+    pub state: ProcessState,
+
+    /// Reason of latest state transition
+    pub state_reason: Option<String>,
+
+    /// Time of latest state transition, in seconds
+    pub state_time: u64,
+
+    /// Recent state transitions, oldest first
+    pub state_history: Vec<ProcessStateTransition>,
+
     /// Port
     pub port: String,
 
@@ -35,6 +55,9 @@ pub struct ModelServerData {
     /// Model ID
     pub model_type: String,
 
+    /// What model server serves: chat, embedding, rerank, image, speech or transcription
+    pub capability: String,
+
     /// ISQ
     pub isq: Option<String>,
 
@@ -76,39 +99,89 @@ pub struct ModelServerData {
 
     /// RoPE frequency base
     pub rope_freq_base: Option<u32>,
+
+    /// Restart model server automatically after service restart
+    pub autostart: bool,
+
+    /// Restart policy
+    pub restart_policy: String,
+
+    /// Count of restarts
+    pub restart_count: u32,
+
+    /// Last exit status of restarted process
+    pub last_exit_status: Option<String>,
+
+    /// Idle timeout in seconds
+    pub idle_timeout: Option<u64>,
+
+    /// Last time a request is seen, in seconds
+    pub last_activity_time: u64,
+
+    /// Replica group
+    pub replica_group: Option<String>,
+
+    /// Routing across replicas
+    pub load_balancing: String,
+
+    /// Count of requests in flight through gateway
+    pub active_requests: u32,
+
+    /// Requests served at same time
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting in queue
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue
+    pub queue_timeout: Option<u64>,
+
+    /// Count of requests waiting in queue of gateway
+    pub queued_requests: u32,
 }
 
-/// Request for Start Model Server
+/// Request for Start Model Server, launch settings which are not provided are taken from profile
 #[derive(Debug, Deserialize)]
 pub struct StartModelServerRequest {
     /// Model Name： Local Model Identifier
     pub model_name: String,
 
+    /// Launch profile, default profile of model is used if it is not provided
+    #[serde(default)]
+    pub profile: Option<String>,
+
     /// Model ID
+    #[serde(default)]
     pub model_id: String,
 
     /// Model ID
+    #[serde(default)]
     pub model_type: String,
 
     /// ISQ
     pub isq: Option<String>,
 
     /// Model Path
+    #[serde(default)]
     pub path: String,
 
     /// Token source
     pub token_source: Option<String>,
 
     ///CPU
-    pub cpu: bool,
+    #[serde(default)]
+    pub cpu: Option<bool>,
 
     ///Offloaded
-    pub offloaded: bool,
+    #[serde(default)]
+    pub offloaded: Option<bool>,
 
     ///Backend
+    #[serde(default)]
     pub backend: String,
 
     /// Acceleration
+    #[serde(default)]
     pub acceleration: String,
 
     /// Context Length
@@ -132,7 +205,57 @@ pub struct StartModelServerRequest {
     /// RoPE frequency base
     pub rope_freq_base: Option<u32>,
 
+    /// Restart model server automatically after service restart
+    #[serde(default)]
+    pub autostart: bool,
+
+    /// Restart policy: never, on-failure or always
+    pub restart_policy: Option<String>,
+
+    /// Max retries for on-failure restart policy
+    pub restart_max_retries: Option<u32>,
+
+    /// Stop model server after no request is seen through gateway for this many seconds
+    pub idle_timeout: Option<u64>,
+
+    /// Start model server again on request after it is unloaded by idle timeout
+    #[serde(default)]
+    pub start_on_demand: bool,
+
+    /// Maximum memory of model process in MB
+    pub memory_limit: Option<u64>,
 
+    /// CPU cores which model process is allowed to run on
+    pub cpu_affinity: Option<Vec<usize>>,
+
+    /// Nice level of model process from -20 to 19
+    pub nice: Option<i32>,
+
+    /// Start model server even if estimated memory exceeds available memory
+    #[serde(default)]
+    pub ignore_memory_check: bool,
+
+    /// Count of replicas to start, requests through gateway are balanced across them
+    pub replicas: Option<u32>,
+
+    /// Routing across replicas: round-robin or least-busy
+    pub load_balancing: Option<String>,
+
+    /// Requests served by model server at same time, requests are not limited if it is not set
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting for model server, more requests are rejected with 429
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue before it is rejected with 503
+    pub queue_timeout: Option<u64>,
+
+    /// LoRA adapters with scales, resolved from lora tasks or private lora files. llama.cpp only.
+    #[serde(default)]
+    pub lora_adapters: Option<Vec<LoraAdapter>>,
+
+    /// Pooling of embedding or rerank model: none, mean, cls, last or rank
+    pub pooling: Option<String>,
 }
 
 /// Response for Start Model Server
@@ -151,10 +274,18 @@ pub struct StartModelServerResponse {
     /// Data
     pub data: Option<ModelServerData>,
 
+    /// Estimated memory of model server, it contains warning if memory is tight
+    pub memory_estimate: Option<MemoryEstimate>,
+
+    /// Task ids of all replicas started, data is about first replica
+    pub replica_task_ids: Vec<String>,
+
 }
 
 
-/// Request for Start Model Server
+/// Request for Stop Model Server. In single process mode only model servers of stable-diffusion.cpp
+/// and mock backends can be stopped, default, llama.cpp and whisper.cpp backends block in their
+/// library until service exits, so stop of them is rejected with error.
 #[derive(Debug, Deserialize)]
 pub struct StopModelServerRequest {
     /// Model ID
@@ -182,7 +313,9 @@ pub struct StopModelServerResponse {
 /// Request for Start Model Server
 #[derive(Debug, Deserialize)]
 pub struct GetModelServersRequest {
-
+    /// Also return model servers stopped or failed recently with their final state and reason
+    #[serde(default)]
+    pub include_finished: bool,
 }
 
 impl From<ModelInfo> for ModelServerData {
@@ -191,9 +324,14 @@ impl From<ModelInfo> for ModelServerData {
             model_name: model_info.model_name,
             task_id: model_info.task_id,
             started: model_info.started,
+            state: model_info.state,
+            state_reason: model_info.state_reason,
+            state_time: model_info.state_time,
+            state_history: model_info.state_history,
             port: model_info.port,
             model_id: model_info.model_id,
             model_type: model_info.model_type,
+            capability: model_info.capability,
             isq: model_info.isq,
             path: model_info.path,
             token_source: model_info.token_source,
@@ -208,6 +346,19 @@ impl From<ModelInfo> for ModelServerData {
             rope_scaling: model_info.rope_scaling,
             rope_scale: model_info.rope_scale,
             rope_freq_base: model_info.rope_freq_base,
+            autostart: model_info.autostart,
+            restart_policy: model_info.restart_policy,
+            restart_count: model_info.restart_count,
+            last_exit_status: model_info.last_exit_status,
+            idle_timeout: model_info.idle_timeout,
+            last_activity_time: model_info.last_activity_time,
+            replica_group: model_info.replica_group,
+            load_balancing: model_info.load_balancing,
+            active_requests: model_info.active_requests,
+            max_concurrency: model_info.max_concurrency,
+            max_queue_size: model_info.max_queue_size,
+            queue_timeout: model_info.queue_timeout,
+            queued_requests: model_info.queued_requests,
         }
     }
 }
@@ -229,61 +380,339 @@ pub struct GetModelServersResponse {
 
 }
 
+/// Request for Get Model Logs
+#[derive(Debug, Deserialize)]
+pub struct GetModelLogsRequest {
+    /// Task ID
+    pub task_id: String,
+
+    /// Count of last lines
+    pub lines: Option<usize>,
+}
+
+/// Response for Get Model Logs
+#[derive(Debug, Serialize)]
+pub struct GetModelLogsResponse {
+    /// Status
+    pub success: bool,
+
+    /// Code
+    pub code: String,
+
+    /// Message
+    pub message: String,
+
+    /// Data
+    pub data: Option<Vec<String>>,
+}
+
+/// Request for Inspect Model
+#[derive(Debug, Deserialize)]
+pub struct InspectModelRequest {
+    /// Task Name
+    pub task_name: String,
+}
+
+/// Response for Inspect Model
+#[derive(Debug, Serialize)]
+pub struct InspectModelResponse {
+    /// Status
+    pub success: bool,
+
+    /// Code
+    pub code: String,
+
+    /// Message
+    pub message: String,
+
+    /// Data
+    pub data: Option<GgufInfo>,
+}
+
+/// Request for List Model Profiles
+#[derive(Debug, Deserialize)]
+pub struct ListModelProfilesRequest {
+    /// Task Name
+    pub task_name: String,
+}
+
+/// Request for Save Model Profile, profile with same name is replaced
+#[derive(Debug, Deserialize)]
+pub struct SaveModelProfileRequest {
+    /// Task Name
+    pub task_name: String,
+
+    /// Profile
+    pub profile: LaunchProfile,
+
+    /// Make profile default profile of task
+    #[serde(default)]
+    pub set_default: bool,
+}
+
+/// Request for Delete Model Profile
+#[derive(Debug, Deserialize)]
+pub struct DeleteModelProfileRequest {
+    /// Task Name
+    pub task_name: String,
+
+    /// Profile Name
+    pub profile_name: String,
+}
+
+/// Request for Set Default Model Profile
+#[derive(Debug, Deserialize)]
+pub struct SetDefaultModelProfileRequest {
+    /// Task Name
+    pub task_name: String,
+
+    /// Profile Name, default profile is cleared if it is not provided
+    pub profile_name: Option<String>,
+}
+
+/// Response for Model Profiles
+#[derive(Debug, Serialize)]
+pub struct ModelProfilesResponse {
+    /// Status
+    pub success: bool,
+
+    /// Code
+    pub code: String,
+
+    /// Message
+    pub message: String,
+
+    /// Data
+    pub data: Option<TaskProfiles>,
+}
+
+/// Request for List Chat Templates
+#[derive(Debug, Deserialize)]
+pub struct ListChatTemplatesRequest {
+    /// Task Name, template attached to task is returned if it is provided
+    pub task_name: Option<String>,
+}
+
+/// Request for Upload Chat Template, uploaded template with same name is replaced
+#[derive(Debug, Deserialize)]
+pub struct UploadChatTemplateRequest {
+    /// Template Name
+    pub template_name: String,
+
+    /// Jinja template content
+    pub content: String,
+}
+
+/// Request for Delete Chat Template
+#[derive(Debug, Deserialize)]
+pub struct DeleteChatTemplateRequest {
+    /// Template Name
+    pub template_name: String,
+}
+
+/// Request for Set Model Chat Template
+#[derive(Debug, Deserialize)]
+pub struct SetModelChatTemplateRequest {
+    /// Task Name
+    pub task_name: String,
+
+    /// Template Name, template is detached from task if it is not provided
+    pub template_name: Option<String>,
+}
+
+/// Chat templates and template attached to task
+#[derive(Debug, Serialize)]
+pub struct ChatTemplatesData {
+    /// Bundled and uploaded templates
+    pub templates: Vec<ChatTemplateInfo>,
+
+    /// Template attached to task
+    pub task_template: Option<String>,
+}
+
+/// Response for Chat Templates
+#[derive(Debug, Serialize)]
+pub struct ChatTemplatesResponse {
+    /// Status
+    pub success: bool,
+
+    /// Code
+    pub code: String,
+
+    /// Message
+    pub message: String,
+
+    /// Data
+    pub data: Option<ChatTemplatesData>,
+}
+
+fn chat_templates_response(result: anyhow::Result<()>, task_name: Option<&str>) -> HttpResponse {
+    let response = match result {
+        Ok(()) => ChatTemplatesResponse {
+            success: true,
+            code: "".to_string(),
+            message: "".to_string(),
+            data: Some(ChatTemplatesData {
+                templates: template_service::get_chat_templates(),
+                task_template: task_name.and_then(template_service::get_task_chat_template),
+            }),
+        },
+        Err(e) => ChatTemplatesResponse {
+            success: false,
+            code: "".to_string(),
+            message: e.to_string(),
+            data: None,
+        },
+    };
+    HttpResponse::Ok().json(response)
+}
+
+fn model_profiles_response(result: anyhow::Result<TaskProfiles>) -> HttpResponse {
+    let response = match result {
+        Ok(task_profiles) => ModelProfilesResponse {
+            success: true,
+            code: "".to_string(),
+            message: "".to_string(),
+            data: Some(task_profiles),
+        },
+        Err(e) => ModelProfilesResponse {
+            success: false,
+            code: "".to_string(),
+            message: e.to_string(),
+            data: None,
+        },
+    };
+    HttpResponse::Ok().json(response)
+}
+
 /// Start Model Server
 #[post("/model/start")]
 async fn start_model_server(req: web::Json<StartModelServerRequest>) -> impl Responder {
+    let profile = match profile_service::resolve_profile(
+        req.model_name.as_str(),
+        req.profile.as_deref(),
+    ) {
+        Ok(profile) => profile.unwrap_or_default(),
+        Err(e) => {
+            let response = StartModelServerResponse {
+                success: false,
+                code: "".to_string(),
+                message: e.to_string(),
+                data: None,
+                memory_estimate: None,
+                replica_task_ids: vec![],
+            };
+            return HttpResponse::Ok().json(response);
+        }
+    };
+    let model_id = if req.model_id.is_empty() {
+        profile.model_id.clone().or_else(|| {
+            fetch_service::load_local_task(req.model_name.as_str()).and_then(|task| task.model_id)
+        })
+    } else {
+        Some(req.model_id.clone())
+    };
     let args = ModelServiceArgs {
         model_name: req.model_name.clone(),
         port: "1236".to_string(),
-        isq: req.isq.clone(),
-        model_id: req.model_id.clone(),
-        model_type: req.model_type.clone(),
+        isq: req.isq.clone().or(profile.isq.clone()),
+        model_id: model_id.unwrap_or_default(),
+        model_type: or_profile(req.model_type.as_str(), &profile.model_type),
         path: Some(req.path.clone()),
         token_source: req.token_source.clone(),
-        cpu: req.cpu,
-        offloaded: req.offloaded,
-        backend: req.backend.clone(),
-        acceleration: req.acceleration.clone(),
-        context_length: req.context_length.clone(),
-        gpu_layers: req.gpu_layers.clone(),
-        cpu_threads: req.cpu_threads.clone(),
-        batch_size: req.batch_size.clone(),
-        rope_scaling: req.rope_scaling.clone(),
-        rope_scale: req.rope_scale.clone(),
-        rope_freq_base: req.rope_freq_base.clone(),
+        cpu: req.cpu.or(profile.cpu).unwrap_or(false),
+        offloaded: req.offloaded.or(profile.offloaded).unwrap_or(false),
+        backend: or_profile(req.backend.as_str(), &profile.backend),
+        acceleration: or_profile(req.acceleration.as_str(), &profile.acceleration),
+        context_length: req.context_length.or(profile.context_length),
+        gpu_layers: req.gpu_layers.or(profile.gpu_layers),
+        cpu_threads: req.cpu_threads.or(profile.cpu_threads),
+        batch_size: req.batch_size.or(profile.batch_size),
+        rope_scaling: req.rope_scaling.clone().or(profile.rope_scaling.clone()),
+        rope_scale: req.rope_scale.or(profile.rope_scale),
+        rope_freq_base: req.rope_freq_base.or(profile.rope_freq_base),
+        autostart: req.autostart,
+        restart_policy: req.restart_policy.clone(),
+        restart_max_retries: req.restart_max_retries,
+        idle_timeout: req.idle_timeout,
+        start_on_demand: req.start_on_demand,
+        memory_limit: req.memory_limit,
+        cpu_affinity: req.cpu_affinity.clone(),
+        nice: req.nice,
+        replica_group: None,
+        load_balancing: req.load_balancing.clone(),
+        max_concurrency: req.max_concurrency,
+        max_queue_size: req.max_queue_size,
+        queue_timeout: req.queue_timeout,
+        lora_adapters: req.lora_adapters.clone(),
+        pooling: req.pooling.clone(),
+        ignore_memory_check: req.ignore_memory_check,
     };
+    // Memory is checked when model server starts, estimate is only reported here
+    let memory_estimate = memory_service::check_model_memory(&args).ok();
     let config = config::get_synvek_config();
     let multi_process = config.multi_process;
-    match model_service::start_model_server_from_web(multi_process, &args).await {
-        Ok(task_id) => {
+    let replicas = req.replicas.unwrap_or(1);
+    match model_service::start_model_server_replicas(multi_process, &args, replicas).await {
+        Ok((task_ids, replica_group)) => {
             let model_server_data = ModelServerData {
                 model_name: req.model_name.clone(),
-                task_id,
+                task_id: task_ids.first().cloned().unwrap_or_default(),
                 started: false,
+                state: ProcessState::Spawning,
+                state_reason: None,
+                state_time: 0,
+                state_history: vec![],
                 // The port is random number and will be updated soon
                 port: "1236".to_string(),
-                model_id: req.model_id.clone(),
-                model_type: req.model_type.clone(),
-                isq: req.isq.clone(),
+                model_id: args.model_id.clone(),
+                model_type: args.model_type.clone(),
+                capability: model_service::get_model_capability(
+                    args.backend.as_str(),
+                    args.model_type.as_str(),
+                )
+                .to_string(),
+                isq: args.isq.clone(),
                 path: req.path.clone(),
                 token_source: req.token_source.clone(),
-                cpu: req.cpu,
-                offloaded: req.offloaded,
-                backend: req.backend.clone(),
-                acceleration: req.acceleration.clone(),
-                context_length: req.context_length.clone(),
-                gpu_layers: req.gpu_layers.clone(),
-                cpu_threads: req.cpu_threads.clone(),
-                batch_size: req.batch_size.clone(),
-                rope_scaling: req.rope_scaling.clone(),
-                rope_scale: req.rope_scale.clone(),
-                rope_freq_base: req.rope_freq_base.clone(),
+                cpu: args.cpu,
+                offloaded: args.offloaded,
+                backend: args.backend.clone(),
+                acceleration: args.acceleration.clone(),
+                context_length: args.context_length,
+                gpu_layers: args.gpu_layers,
+                cpu_threads: args.cpu_threads,
+                batch_size: args.batch_size,
+                rope_scaling: args.rope_scaling.clone(),
+                rope_scale: args.rope_scale,
+                rope_freq_base: args.rope_freq_base,
+                autostart: req.autostart,
+                restart_policy: req
+                    .restart_policy
+                    .clone()
+                    .unwrap_or(common::RESTART_POLICY_NEVER.to_string()),
+                restart_count: 0,
+                last_exit_status: None,
+                idle_timeout: req.idle_timeout,
+                last_activity_time: 0,
+                replica_group,
+                load_balancing: req
+                    .load_balancing
+                    .clone()
+                    .unwrap_or(common::LOAD_BALANCING_ROUND_ROBIN.to_string()),
+                active_requests: 0,
+                max_concurrency: req.max_concurrency,
+                max_queue_size: req.max_queue_size,
+                queue_timeout: req.queue_timeout,
+                queued_requests: 0,
             };
             let response = StartModelServerResponse {
                 success: true,
                 code: "".to_string(),
                 message: "".to_string(),
                 data: Option::from(model_server_data),
+                memory_estimate,
+                replica_task_ids: task_ids,
             };
             HttpResponse::Ok().json(response)
         }
@@ -293,21 +722,34 @@ async fn start_model_server(req: web::Json<StartModelServerRequest>) -> impl Res
                 code: "".to_string(),
                 message: e.to_string(),
                 data: None,
+                memory_estimate,
+                replica_task_ids: vec![],
             };
             HttpResponse::Ok().json(response)
         }
     }
 }
 
-/// Get Running Model Servers
+/// Value of start request, or value of profile if it is not provided on request
+fn or_profile(value: &str, profile_value: &Option<String>) -> String {
+    if value.is_empty() {
+        profile_value.clone().unwrap_or_default()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Stop Model Server
 #[post("/model/stop")]
 async fn stop_model_server(req: web::Json<StopModelServerRequest>) -> impl Responder {
-    model_service::stop_model_server(req.task_id.as_str());
+    let result = model_service::stop_model_server(req.task_id.as_str());
     let response = StartModelServerResponse {
-        success: true,
+        success: result.is_ok(),
         code: "".to_string(),
-        message: "".to_string(),
+        message: result.err().map(|e| e.to_string()).unwrap_or_default(),
         data: None,
+        memory_estimate: None,
+        replica_task_ids: vec![],
     };
     HttpResponse::Ok().json(response)
 }
@@ -315,7 +757,10 @@ async fn stop_model_server(req: web::Json<StopModelServerRequest>) -> impl Respo
 /// Get Model Servers
 #[post("/model/servers")]
 async fn get_model_servers(req: web::Json<GetModelServersRequest>) -> impl Responder {
-    let model_servers = model_service::get_model_servers();
+    let mut model_servers = model_service::get_model_servers();
+    if req.include_finished {
+        model_servers.extend(model_service::get_finished_model_servers());
+    }
     let model_servers_data: Vec<ModelServerData> = model_servers.into_iter().map(ModelServerData::from).collect();
     let response = GetModelServersResponse {
         success: true,
@@ -325,3 +770,172 @@ async fn get_model_servers(req: web::Json<GetModelServersRequest>) -> impl Respo
     };
     HttpResponse::Ok().json(response)
 }
+
+/// List Model Profiles
+#[post("/model/profile/list")]
+async fn list_model_profiles(req: web::Json<ListModelProfilesRequest>) -> impl Responder {
+    model_profiles_response(Ok(profile_service::get_task_profiles(
+        req.task_name.as_str(),
+    )))
+}
+
+/// Save Model Profile
+#[post("/model/profile/save")]
+async fn save_model_profile(req: web::Json<SaveModelProfileRequest>) -> impl Responder {
+    model_profiles_response(profile_service::save_profile(
+        req.task_name.as_str(),
+        &req.profile,
+        req.set_default,
+    ))
+}
+
+/// Delete Model Profile
+#[post("/model/profile/delete")]
+async fn delete_model_profile(req: web::Json<DeleteModelProfileRequest>) -> impl Responder {
+    model_profiles_response(profile_service::delete_profile(
+        req.task_name.as_str(),
+        req.profile_name.as_str(),
+    ))
+}
+
+/// Set Default Model Profile
+#[post("/model/profile/default")]
+async fn set_default_model_profile(
+    req: web::Json<SetDefaultModelProfileRequest>,
+) -> impl Responder {
+    model_profiles_response(profile_service::set_default_profile(
+        req.task_name.as_str(),
+        req.profile_name.clone(),
+    ))
+}
+
+/// List Chat Templates
+#[post("/model/chat-template/list")]
+async fn list_chat_templates(req: web::Json<ListChatTemplatesRequest>) -> impl Responder {
+    chat_templates_response(Ok(()), req.task_name.as_deref())
+}
+
+/// Upload Chat Template
+#[post("/model/chat-template/upload")]
+async fn upload_chat_template(req: web::Json<UploadChatTemplateRequest>) -> impl Responder {
+    chat_templates_response(
+        template_service::save_chat_template(req.template_name.as_str(), req.content.as_str()),
+        None,
+    )
+}
+
+/// Delete Chat Template
+#[post("/model/chat-template/delete")]
+async fn delete_chat_template(req: web::Json<DeleteChatTemplateRequest>) -> impl Responder {
+    chat_templates_response(
+        template_service::delete_chat_template(req.template_name.as_str()),
+        None,
+    )
+}
+
+/// Set Model Chat Template, it is used when model server starts next time
+#[post("/model/chat-template/set")]
+async fn set_model_chat_template(req: web::Json<SetModelChatTemplateRequest>) -> impl Responder {
+    chat_templates_response(
+        template_service::set_task_chat_template(
+            req.task_name.as_str(),
+            req.template_name.clone(),
+        ),
+        Some(req.task_name.as_str()),
+    )
+}
+
+/// Inspect Model
+#[post("/model/inspect")]
+async fn inspect_model(req: web::Json<InspectModelRequest>) -> impl Responder {
+    match model_service::inspect_model(req.task_name.as_str()) {
+        Ok(gguf_info) => {
+            let response = InspectModelResponse {
+                success: true,
+                code: "".to_string(),
+                message: "".to_string(),
+                data: Some(gguf_info),
+            };
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            let response = InspectModelResponse {
+                success: false,
+                code: "".to_string(),
+                message: e.to_string(),
+                data: None,
+            };
+            HttpResponse::Ok().json(response)
+        }
+    }
+}
+
+/// Get Model Logs
+#[post("/model/logs")]
+async fn get_model_logs(req: web::Json<GetModelLogsRequest>) -> impl Responder {
+    let lines = req.lines.unwrap_or(common::MODEL_LOG_DEFAULT_LINES);
+    match model_log_service::read_model_log(req.task_id.as_str(), lines) {
+        Ok((log_lines, _)) => {
+            let response = GetModelLogsResponse {
+                success: true,
+                code: "".to_string(),
+                message: "".to_string(),
+                data: Some(log_lines),
+            };
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => {
+            let response = GetModelLogsResponse {
+                success: false,
+                code: "".to_string(),
+                message: e.to_string(),
+                data: None,
+            };
+            HttpResponse::Ok().json(response)
+        }
+    }
+}
+
+/// Follow Model Logs, last lines are sent first and then new lines once they are written
+#[post("/model/logs/stream")]
+async fn get_model_logs_stream(req: web::Json<GetModelLogsRequest>) -> impl Responder {
+    let task_id = req.task_id.clone();
+    let lines = req.lines.unwrap_or(common::MODEL_LOG_DEFAULT_LINES);
+    if let Err(e) = model_log_service::validate_task_id(task_id.as_str()) {
+        let response = GetModelLogsResponse {
+            success: false,
+            code: "".to_string(),
+            message: e.to_string(),
+            data: None,
+        };
+        return HttpResponse::Ok().json(response);
+    }
+    let sse_stream = stream! {
+        let mut offset: u64 = 0;
+        match model_log_service::read_model_log(task_id.as_str(), lines) {
+            Ok((log_lines, file_size)) => {
+                offset = file_size;
+                for log_line in log_lines.iter() {
+                    let json = serde_json::to_string(log_line).unwrap();
+                    yield Ok::<_, actix_web::Error>(Bytes::from(format!("data: {}\n\n", json)));
+                }
+            }
+            Err(e) => {
+                tracing::debug!("Model log isn't ready on task {}: {}", task_id, e);
+            }
+        }
+        loop {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            if let Ok((log_lines, new_offset)) = model_log_service::read_model_log_from(task_id.as_str(), offset) {
+                offset = new_offset;
+                for log_line in log_lines.iter() {
+                    let json = serde_json::to_string(log_line).unwrap();
+                    yield Ok::<_, actix_web::Error>(Bytes::from(format!("data: {}\n\n", json)));
+                }
+            }
+        }
+    };
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(sse_stream)
+}
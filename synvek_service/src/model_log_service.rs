@@ -0,0 +1,218 @@
+use crate::common;
+use crate::config::Config;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use std::{fs, thread};
+use uuid::Uuid;
+
+/// Log writer for output of model process, log file is rotated when it exceeds max size
+pub struct ModelLogWriter {
+    log_file: PathBuf,
+
+    file: Option<File>,
+
+    size: u64,
+}
+
+impl ModelLogWriter {
+    pub fn new(task_id: &str) -> Self {
+        let log_file = get_model_log_file(task_id);
+        let mut writer = Self {
+            log_file,
+            file: None,
+            size: 0,
+        };
+        writer.open();
+        writer
+    }
+
+    fn open(&mut self) {
+        if let Some(log_dir) = self.log_file.parent() {
+            let _ = fs::create_dir_all(log_dir);
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_file.clone());
+        match file {
+            Ok(file) => {
+                self.size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+                self.file = Some(file);
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Failed to open model log file: {} with error: {}",
+                    self.log_file.display(),
+                    e
+                );
+                self.file = None;
+            }
+        }
+    }
+
+    fn rotate(&mut self) {
+        self.file = None;
+        for index in (1..common::MODEL_LOG_MAX_FILES).rev() {
+            let source = get_rotated_log_file(&self.log_file, index);
+            if source.exists() {
+                let _ = fs::rename(source, get_rotated_log_file(&self.log_file, index + 1));
+            }
+        }
+        let _ = fs::rename(
+            self.log_file.clone(),
+            get_rotated_log_file(&self.log_file, 1),
+        );
+        self.open();
+    }
+
+    pub fn write(&mut self, data: &[u8]) {
+        if let Some(file) = self.file.as_mut() {
+            if file.write_all(data).is_ok() {
+                self.size += data.len() as u64;
+            }
+        }
+        if self.size >= common::MODEL_LOG_MAX_SIZE {
+            self.rotate();
+        }
+    }
+}
+
+fn get_rotated_log_file(log_file: &PathBuf, index: u32) -> PathBuf {
+    let mut rotated_log_file = log_file.clone().into_os_string();
+    rotated_log_file.push(format!(".{}", index));
+    PathBuf::from(rotated_log_file)
+}
+
+/// Task id is used as file name and only uuid is accepted
+pub fn validate_task_id(task_id: &str) -> anyhow::Result<()> {
+    if Uuid::parse_str(task_id).is_err() {
+        return Err(anyhow::anyhow!("Invalid task id: {}", task_id));
+    }
+    Ok(())
+}
+
+pub fn get_model_log_file(task_id: &str) -> PathBuf {
+    let config = Config::new();
+    let mut log_file = config.get_log_dir();
+    log_file.push(common::MODEL_LOG_DIR_NAME);
+    log_file.push(format!("{}.log", task_id));
+    log_file
+}
+
+/// Remove log file of task and its rotated files
+pub fn remove_model_log(task_id: &str) {
+    let log_file = get_model_log_file(task_id);
+    let _ = fs::remove_file(&log_file);
+    for index in 1..=common::MODEL_LOG_MAX_FILES {
+        let _ = fs::remove_file(get_rotated_log_file(&log_file, index));
+    }
+}
+
+/// Remove logs of tasks left by previous service runs except latest ones. Logs of tasks finished in
+/// this run are removed once they leave recently finished processes.
+pub fn prune_model_logs() {
+    let config = Config::new();
+    let mut log_dir = config.get_log_dir();
+    log_dir.push(common::MODEL_LOG_DIR_NAME);
+    let Ok(entries) = fs::read_dir(&log_dir) else {
+        return;
+    };
+    let mut task_logs: Vec<(String, SystemTime)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "log" {
+                return None;
+            }
+            let task_id = path.file_stem()?.to_str()?.to_string();
+            validate_task_id(task_id.as_str()).ok()?;
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            Some((task_id, modified))
+        })
+        .collect();
+    task_logs.sort_by(|a, b| b.1.cmp(&a.1));
+    task_logs
+        .iter()
+        .skip(common::MODEL_LOG_MAX_TASKS)
+        .for_each(|(task_id, _)| remove_model_log(task_id.as_str()));
+}
+
+/// Redirect stdout and stderr of model process into log file of task.
+pub fn capture_process_output(task_id: &str, child: &mut Child) {
+    let writer = Arc::new(Mutex::new(ModelLogWriter::new(task_id)));
+    if let Some(stdout) = child.stdout.take() {
+        let writer = Arc::clone(&writer);
+        let _ = thread::spawn(move || copy_process_output(stdout, writer));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        let writer = Arc::clone(&writer);
+        let _ = thread::spawn(move || copy_process_output(stderr, writer));
+    }
+}
+
+fn copy_process_output<R: Read>(output: R, writer: Arc<Mutex<ModelLogWriter>>) {
+    let mut reader = BufReader::new(output);
+    let mut line: Vec<u8> = vec![];
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {
+                let mut writer = writer.lock().unwrap();
+                writer.write(&line);
+            }
+            Err(e) => {
+                tracing::error!("Failed to read model process output with error: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Read last lines of model log, current file size is returned too so log can be followed from there
+pub fn read_model_log(task_id: &str, lines: usize) -> anyhow::Result<(Vec<String>, u64)> {
+    validate_task_id(task_id)?;
+    let log_file = get_model_log_file(task_id);
+    let mut file = File::open(log_file.clone())
+        .map_err(|e| anyhow::anyhow!("Failed to open model log: {} with error: {}", log_file.display(), e))?;
+    let file_size = file.metadata()?.len();
+    let start = file_size.saturating_sub(common::MODEL_LOG_TAIL_SIZE);
+    file.seek(SeekFrom::Start(start))?;
+    let mut data: Vec<u8> = vec![];
+    file.read_to_end(&mut data)?;
+    let content = String::from_utf8_lossy(&data);
+    let mut log_lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+    // First line may be partial when reading from middle of file
+    if start > 0 && !log_lines.is_empty() {
+        log_lines.remove(0);
+    }
+    let skip = log_lines.len().saturating_sub(lines);
+    Ok((log_lines.split_off(skip), file_size))
+}
+
+/// Read complete lines appended after offset, new offset is returned. Log is read from beginning
+/// again if it is rotated in the meantime.
+pub fn read_model_log_from(task_id: &str, offset: u64) -> anyhow::Result<(Vec<String>, u64)> {
+    validate_task_id(task_id)?;
+    let log_file = get_model_log_file(task_id);
+    let mut file = File::open(log_file)?;
+    let file_size = file.metadata()?.len();
+    let offset = if file_size < offset { 0 } else { offset };
+    if file_size == offset {
+        return Ok((vec![], offset));
+    }
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data: Vec<u8> = vec![];
+    file.read_to_end(&mut data)?;
+    let Some(last_line_end) = data.iter().rposition(|byte| *byte == b'\n') else {
+        return Ok((vec![], offset));
+    };
+    data.truncate(last_line_end + 1);
+    let content = String::from_utf8_lossy(&data);
+    let log_lines: Vec<String> = content.lines().map(|line| line.to_string()).collect();
+    Ok((log_lines, offset + data.len() as u64))
+}
@@ -1,33 +1,38 @@
+use crate::backend_service::BackendContext;
 use crate::common::MODEL_SOURCE_MODELSCOPE;
 use crate::config::Config;
-use crate::fetch_service::Task;
+use crate::fetch_service::{Task, TaskItem};
+use crate::gguf_helper::GgufInfo;
 use crate::process_api::{HeartTickRequest, HeartTickResponse};
-use crate::process_service::notify_main_process;
+use crate::process_service::{ProcessInfo, ProcessState, ProcessStateTransition, ProcessType};
+use crate::process_service::{notify_main_process, notify_main_process_state};
+use crate::system_service::{MessageSource, MessageType};
 use crate::script_service::ScriptInfo;
-use crate::{common, fetch_service, sd_server};
-use crate::{config, process_service, synvek};
-use crate::{modelscope_helper, utils};
+use crate::{backend_service, common, concurrency_service, fetch_service, memory_service, resource_service, sd_server};
+use crate::{config, process_service, synvek, system_service, template_service};
+use crate::{gguf_helper, modelscope_helper, utils};
 use async_trait::async_trait;
 use clap::Subcommand;
 use libloading::{Library, Symbol};
 use log::trace;
 use reqwest::{Client, header};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::{CStr, CString, OsString, c_char, c_int};
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::{Arc, Mutex, Once, OnceLock};
 use std::thread::sleep;
 use std::time::Duration;
-use std::{env, panic, thread};
+use std::{env, fs, panic, thread};
 use actix_web::body::MessageBody;
 use tokio::runtime;
 use uuid::Uuid;
 use crate::sd_service::get_sd_config;
 
-type StartLlamaServer = unsafe fn(i32, *const *const c_char) -> i32;
 type StartSdServer = unsafe fn(i32, *const *const c_char) -> i32;
 type InitDefaultServer =
     unsafe fn(*const c_char, *const c_char, *const c_char, *const c_char) -> i32;
@@ -54,7 +59,7 @@ pub enum ModelSelected {
     Speech,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ModelServiceArgs {
     /// port
     pub port: String,
@@ -113,6 +118,139 @@ pub struct ModelServiceArgs {
     /// RoPE frequency base
     pub rope_freq_base: Option<u32>,
 
+    /// Restart model server automatically after service restart
+    #[serde(default)]
+    pub autostart: bool,
+
+    /// Restart policy when model process exits: never, on-failure or always. Multiple process mode only.
+    #[serde(default)]
+    pub restart_policy: Option<String>,
+
+    /// Max retries for on-failure restart policy
+    #[serde(default)]
+    pub restart_max_retries: Option<u32>,
+
+    /// Stop model server after no request is seen through gateway for this many seconds. Multiple process mode only.
+    #[serde(default)]
+    pub idle_timeout: Option<u64>,
+
+    /// Start model server again when a request arrives after it is unloaded by idle timeout
+    #[serde(default)]
+    pub start_on_demand: bool,
+
+    /// Maximum memory of model process in MB. It is applied by cgroup v2 with memory controller
+    /// delegated, and ignored without it. Multiple process mode only.
+    #[serde(default)]
+    pub memory_limit: Option<u64>,
+
+    /// CPU cores which model process is allowed to run on. Multiple process mode only.
+    #[serde(default)]
+    pub cpu_affinity: Option<Vec<usize>>,
+
+    /// Nice level of model process from -20 to 19. Multiple process mode only.
+    #[serde(default)]
+    pub nice: Option<i32>,
+
+    /// Replica group which model server belongs to, it is shared by replicas started together
+    #[serde(default)]
+    pub replica_group: Option<String>,
+
+    /// Routing across replicas of model: round-robin or least-busy
+    #[serde(default)]
+    pub load_balancing: Option<String>,
+
+    /// Requests served by model server at same time, requests are not limited if it is not set
+    #[serde(default)]
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting for model server, more requests are rejected with 429
+    #[serde(default)]
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue before it is rejected with 503
+    #[serde(default)]
+    pub queue_timeout: Option<u64>,
+
+    /// LoRA adapters applied over base model, llama.cpp backend only
+    #[serde(default)]
+    pub lora_adapters: Option<Vec<LoraAdapter>>,
+
+    /// Pooling of embedding or rerank model: none, mean, cls, last or rank. Model default is used
+    /// if it is not set.
+    #[serde(default)]
+    pub pooling: Option<String>,
+
+    /// Start model server even if estimated memory exceeds available memory
+    #[serde(default)]
+    pub ignore_memory_check: bool,
+}
+
+/// LoRA adapter of lora task or private lora file in lora dir
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LoraAdapter {
+    /// Task name of lora model or file name of private lora model
+    pub name: String,
+
+    /// Scale of adapter, adapter is applied with full scale if it is not set
+    #[serde(default)]
+    pub scale: Option<f32>,
+}
+
+/// Adapter is written as name or name:scale in command line
+impl FromStr for LoraAdapter {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some((name, scale)) = value.rsplit_once(':') {
+            if let Ok(scale) = scale.parse::<f32>() {
+                return Ok(LoraAdapter {
+                    name: name.to_string(),
+                    scale: Some(scale),
+                });
+            }
+        }
+        if value.is_empty() {
+            return Err(anyhow::anyhow!("LoRA adapter name is required"));
+        }
+        Ok(LoraAdapter {
+            name: value.to_string(),
+            scale: None,
+        })
+    }
+}
+
+impl fmt::Display for LoraAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.scale {
+            Some(scale) => write!(f, "{}:{}", self.name, scale),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Model server started in multiple process mode, it is persisted to restore after service restart
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModelServerConfig {
+    pub task_id: String,
+
+    pub args: ModelServiceArgs,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ModelServerConfigs {
+    pub model_servers: Vec<ModelServerConfig>,
+}
+
+static MODEL_SERVERS_CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+static IDLE_MONITOR: Once = Once::new();
+
+/// Model servers unloaded by idle timeout which can be started on demand, keyed by model name
+static UNLOADED_MODEL_SERVERS: OnceLock<Arc<Mutex<HashMap<String, ModelServiceArgs>>>> =
+    OnceLock::new();
+
+fn init_unloaded_model_servers() -> Arc<Mutex<HashMap<String, ModelServiceArgs>>> {
+    Arc::new(Mutex::new(HashMap::new()))
 }
 
 #[derive(Debug, Clone)]
@@ -129,6 +267,18 @@ pub struct ModelInfo {
     /// If server started
     pub started: bool,
 
+    /// Lifecycle state
+    pub state: ProcessState,
+
+    /// Reason of latest state transition
+    pub state_reason: Option<String>,
+
+    /// Time of latest state transition, in seconds
+    pub state_time: u64,
+
+    /// Recent state transitions, oldest first
+    pub state_history: Vec<ProcessStateTransition>,
+
     /// in_situ_quant
     pub isq: Option<String>,
 
@@ -138,6 +288,9 @@ pub struct ModelInfo {
     /// Model
     pub model_type: String,
 
+    /// What model server serves: chat, embedding, rerank, image, speech or transcription
+    pub capability: String,
+
     /// Model Path
     pub path: String,
 
@@ -175,49 +328,120 @@ pub struct ModelInfo {
 
     /// RoPE frequency base
     pub rope_freq_base: Option<u32>,
+
+    /// Restart model server automatically after service restart
+    pub autostart: bool,
+
+    /// Restart policy
+    pub restart_policy: String,
+
+    /// Count of restarts
+    pub restart_count: u32,
+
+    /// Last exit status of restarted process
+    pub last_exit_status: Option<String>,
+
+    /// Idle timeout in seconds
+    pub idle_timeout: Option<u64>,
+
+    /// Last time a request is seen, in seconds
+    pub last_activity_time: u64,
+
+    /// Replica group
+    pub replica_group: Option<String>,
+
+    /// Routing across replicas
+    pub load_balancing: String,
+
+    /// Count of requests in flight through gateway
+    pub active_requests: u32,
+
+    /// Requests served at same time
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting in queue
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue
+    pub queue_timeout: Option<u64>,
+
+    /// Count of requests waiting in queue of gateway
+    pub queued_requests: u32,
 }
 
+/// Model servers in both modes, model servers running in this process are registered in process
+/// service same as model processes.
 pub fn get_model_servers() -> Vec<ModelInfo> {
-    let synvek_config = config::get_synvek_config();
-    if synvek_config.multi_process {
-        let process_infos = process_service::get_processes();
-        let mut model_infos: Vec<ModelInfo> = vec![];
-        process_infos.iter().for_each(|info| {
-            let model_info = ModelInfo {
-                model_name: info.model_name.clone(),
-                task_id: info.task_id.clone(),
-                port: info.port.clone(),
-                started: info.running,
-                isq: info.isq.clone(),
-                model_id: info.model_id.clone(),
-                model_type: info.model_type.clone(),
-                path: if info.path.is_some() {
-                    info.path.clone().unwrap()
-                } else {
-                    "".to_string()
-                },
-                token_source: info.token_source.clone(),
-                cpu: info.cpu,
-                offloaded: info.offloaded,
-                backend: info.backend.clone(),
-                acceleration: info.acceleration.clone(),
-                context_length: info.context_length.clone(),
-                gpu_layers: info.gpu_layers.clone(),
-                cpu_threads: info.cpu_threads.clone(),
-                batch_size: info.batch_size.clone(),
-                rope_scaling: info.rope_scaling.clone(),
-                rope_scale: info.rope_scale.clone(),
-                rope_freq_base: info.rope_freq_base.clone(),
-            };
-            model_infos.push(model_info);
-        });
-        model_infos
-    } else {
-        //mistralrs_server::get_servers()
-        vec![]
+    process_service::get_processes()
+        .iter()
+        .map(create_model_info)
+        .collect()
+}
+
+/// Model servers stopped or failed recently, they keep final state and its reason
+pub fn get_finished_model_servers() -> Vec<ModelInfo> {
+    process_service::get_finished_processes()
+        .iter()
+        .map(create_model_info)
+        .collect()
+}
+
+fn create_model_info(info: &ProcessInfo) -> ModelInfo {
+    ModelInfo {
+        model_name: info.model_name.clone(),
+        task_id: info.task_id.clone(),
+        port: info.port.clone(),
+        started: info.is_ready(),
+        state: info.state.clone(),
+        state_reason: info.state_reason(),
+        state_time: info.state_time(),
+        state_history: info.state_history.clone(),
+        isq: info.isq.clone(),
+        model_id: info.model_id.clone(),
+        model_type: info.model_type.clone(),
+        capability: get_model_capability(info.backend.as_str(), info.model_type.as_str())
+            .to_string(),
+        path: if info.path.is_some() {
+            info.path.clone().unwrap()
+        } else {
+            "".to_string()
+        },
+        token_source: info.token_source.clone(),
+        cpu: info.cpu,
+        offloaded: info.offloaded,
+        backend: info.backend.clone(),
+        acceleration: info.acceleration.clone(),
+        context_length: info.context_length.clone(),
+        gpu_layers: info.gpu_layers.clone(),
+        cpu_threads: info.cpu_threads.clone(),
+        batch_size: info.batch_size.clone(),
+        rope_scaling: info.rope_scaling.clone(),
+        rope_scale: info.rope_scale.clone(),
+        rope_freq_base: info.rope_freq_base.clone(),
+        autostart: info.autostart,
+        restart_policy: info.restart_policy.clone(),
+        restart_count: info.restart_count,
+        last_exit_status: info.last_exit_status.clone(),
+        idle_timeout: info.idle_timeout,
+        last_activity_time: info.last_activity_time,
+        replica_group: info.replica_group.clone(),
+        load_balancing: info.load_balancing.clone(),
+        active_requests: info.active_requests,
+        max_concurrency: info.max_concurrency,
+        max_queue_size: info.max_queue_size,
+        queue_timeout: info.queue_timeout,
+        queued_requests: concurrency_service::get_queued_requests(info.task_id.as_str())
+            as u32,
     }
 }
 
+/// Capability of model server, it tells which OpenAI compatible endpoint model server serves
+pub fn get_model_capability(backend: &str, model_type: &str) -> &'static str {
+    backend_service::get_backend(backend)
+        .map(|backend| backend.capability(model_type))
+        .unwrap_or(common::CAPABILITY_CHAT)
+}
+
 pub fn initialize_model_server() {
     let config = Config::new();
     let model_dir = config.get_model_dir();
@@ -243,6 +467,79 @@ pub async fn start_model_server_from_web(
     multi_process: bool,
     args: &ModelServiceArgs,
 ) -> Result<String, anyhow::Error> {
+    if let Some(restart_policy) = args.restart_policy.clone() {
+        if restart_policy != common::RESTART_POLICY_NEVER
+            && restart_policy != common::RESTART_POLICY_ON_FAILURE
+            && restart_policy != common::RESTART_POLICY_ALWAYS
+        {
+            return Err(anyhow::anyhow!("Invalid restart policy: {}", restart_policy));
+        }
+    }
+    if let Some(nice) = args.nice {
+        if !(-20..=19).contains(&nice) {
+            return Err(anyhow::anyhow!("Invalid nice level: {}", nice));
+        }
+    }
+    if let Some(cpu_affinity) = args.cpu_affinity.as_ref() {
+        if cpu_affinity.is_empty() {
+            return Err(anyhow::anyhow!("CPU affinity can't be empty"));
+        }
+    }
+    if args.memory_limit.is_some() {
+        if !multi_process {
+            return Err(anyhow::anyhow!(
+                "Memory limit is supported in multiple process mode only"
+            ));
+        }
+        resource_service::check_memory_limit()
+            .map_err(|e| anyhow::anyhow!("Memory limit can't be applied: {}", e))?;
+    }
+    if args.max_concurrency == Some(0) {
+        return Err(anyhow::anyhow!("Max concurrency must be greater than 0"));
+    }
+    if !backend_service::validate_model_type(args.backend.as_str(), args.model_type.as_str()) {
+        return Err(anyhow::anyhow!(
+            "Model type {} is not supported on backend {}",
+            args.model_type,
+            args.backend
+        ));
+    }
+    if let Some(pooling) = args.pooling.as_ref() {
+        if !common::POOLING_TYPES.contains(&pooling.as_str()) {
+            return Err(anyhow::anyhow!("Invalid pooling: {}", pooling));
+        }
+        if args.model_type == common::MODEL_TYPE_RERANK && pooling != "rank" {
+            return Err(anyhow::anyhow!("Rerank model requires rank pooling"));
+        }
+    }
+    if let Some(lora_adapters) = args.lora_adapters.as_ref() {
+        let supports_lora = backend_service::get_backend(args.backend.as_str())
+            .map(|backend| backend.supports_lora())
+            .unwrap_or(false);
+        if !lora_adapters.is_empty() && !supports_lora {
+            return Err(anyhow::anyhow!(
+                "LoRA adapters are not supported on backend {}",
+                args.backend
+            ));
+        }
+        for lora_adapter in lora_adapters.iter() {
+            get_lora_adapter_path(lora_adapter.name.as_str())?;
+        }
+    }
+    if let Some(load_balancing) = args.load_balancing.clone() {
+        if load_balancing != common::LOAD_BALANCING_ROUND_ROBIN
+            && load_balancing != common::LOAD_BALANCING_LEAST_BUSY
+        {
+            return Err(anyhow::anyhow!("Invalid load balancing: {}", load_balancing));
+        }
+    }
+    // Checked on every start path, so restored, on demand and replica starts can't exceed memory either
+    if let Err(e) = memory_service::check_model_memory(args) {
+        if !args.ignore_memory_check {
+            return Err(e);
+        }
+        tracing::warn!("Memory check is ignored on model {}: {}", args.model_name, e);
+    }
     if multi_process {
         start_model_server_in_spawn_process(args).await
     } else {
@@ -250,10 +547,50 @@ pub async fn start_model_server_from_web(
     }
 }
 
+/// Start replicas of model server in one replica group, gateway routes requests of model across
+/// ready replicas. Task ids of replicas and replica group are returned.
+pub async fn start_model_server_replicas(
+    multi_process: bool,
+    args: &ModelServiceArgs,
+    replicas: u32,
+) -> Result<(Vec<String>, Option<String>), anyhow::Error> {
+    if replicas == 0 || replicas > common::MAX_REPLICAS {
+        return Err(anyhow::anyhow!(
+            "Replicas must be between 1 and {}",
+            common::MAX_REPLICAS
+        ));
+    }
+    if replicas == 1 {
+        let task_id = start_model_server_from_web(multi_process, args).await?;
+        return Ok((vec![task_id], None));
+    }
+    if !multi_process {
+        return Err(anyhow::anyhow!(
+            "Replicas are supported in multiple process mode only"
+        ));
+    }
+    let mut replica_args = args.clone();
+    replica_args.replica_group = Some(Uuid::new_v4().to_string());
+    let mut task_ids: Vec<String> = vec![];
+    for _ in 0..replicas {
+        match start_model_server_from_web(multi_process, &replica_args).await {
+            Ok(task_id) => task_ids.push(task_id),
+            Err(e) => {
+                // Replicas which are started already are stopped so group isn't left partial
+                task_ids.iter().for_each(|task_id| {
+                    let _ = stop_model_server(task_id.as_str());
+                });
+                return Err(e);
+            }
+        }
+    }
+    Ok((task_ids, replica_args.replica_group))
+}
+
 async fn start_model_server_in_spawn_process(
     args: &ModelServiceArgs,
 ) -> Result<String, anyhow::Error> {
-    let new_port_number = synvek::inc_port_number();
+    let new_port_number = synvek::allocate_port_number()?;
     let config = Config::new();
     tracing::info!("Starting model server on port {}", new_port_number);
 
@@ -326,69 +663,99 @@ async fn start_model_server_in_spawn_process(
         process_args.push("--rope-freq-base".to_string());
         process_args.push(rope_freq_base.to_string());
     }
+    if let Some(pooling) = updated_args.pooling.clone() {
+        process_args.push("--pooling".to_string());
+        process_args.push(pooling);
+    }
+    if let Some(lora_adapters) = updated_args.lora_adapters.as_ref() {
+        lora_adapters.iter().for_each(|lora_adapter| {
+            process_args.push("--lora-adapter".to_string());
+            process_args.push(lora_adapter.to_string());
+        });
+    }
+    if let Some(max_concurrency) = updated_args.max_concurrency {
+        process_args.push("--max-concurrency".to_string());
+        process_args.push(max_concurrency.to_string());
+    }
+    if let Some(max_queue_size) = updated_args.max_queue_size {
+        process_args.push("--max-queue-size".to_string());
+        process_args.push(max_queue_size.to_string());
+    }
+    if let Some(queue_timeout) = updated_args.queue_timeout {
+        process_args.push("--queue-timeout".to_string());
+        process_args.push(queue_timeout.to_string());
+    }
 
     tracing::info!("Starting model server {:?}", process_args);
+    if updated_args.idle_timeout.is_some() {
+        start_idle_monitor();
+    }
+    update_model_servers_config(task_id.as_str(), &updated_args);
     process_service::start_process(task_id.to_string(), process_args, updated_args);
     tracing::info!("Starting process on task {}", task_id.to_string());
 
     Ok(task_id.to_string())
 }
 
-fn validate_acceleration(backend: &str, acceleration: &str) -> bool {
-    let mut validation = false;
-    if backend == common::BACKEND_DEFAULT {
-        validation = if cfg!(target_os = "windows") {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_CUDA_LEGACY
-        } else if cfg!(target_os = "macos") {
-            acceleration == common::ACCELERATION_CPU || acceleration == common::ACCELERATION_METAL
-        } else {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_CUDA_LEGACY
-        };
-    } else if backend == common::BACKEND_LLAMA_CPP {
-        validation = if cfg!(target_os = "windows") {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-                || acceleration == common::ACCELERATION_HIP
-        } else if cfg!(target_os = "macos") {
-            acceleration == common::ACCELERATION_CPU || acceleration == common::ACCELERATION_METAL
-        } else {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-        };
-    } else if backend == common::BACKEND_STABLE_DIFFUSION_CPP {
-        validation = if cfg!(target_os = "windows") {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-                || acceleration == common::ACCELERATION_HIP
-        } else if cfg!(target_os = "macos") {
-            acceleration == common::ACCELERATION_CPU || acceleration == common::ACCELERATION_METAL
-        } else {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-        };
-    } else if backend == common::BACKEND_WHISPER_CPP {
-        validation = if cfg!(target_os = "windows") {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-                || acceleration == common::ACCELERATION_HIP
-        } else if cfg!(target_os = "macos") {
-            acceleration == common::ACCELERATION_CPU || acceleration == common::ACCELERATION_METAL
-        } else {
-            acceleration == common::ACCELERATION_CPU
-                || acceleration == common::ACCELERATION_CUDA
-                || acceleration == common::ACCELERATION_VULKAN
-        };
+fn start_idle_monitor() {
+    IDLE_MONITOR.call_once(|| {
+        let _ = thread::spawn(|| loop {
+            sleep(Duration::from_secs(common::IDLE_CHECK_INTERVAL_SECS));
+            process_service::get_idle_processes()
+                .iter()
+                .filter(|process_info| can_stop_model_server(process_info))
+                .for_each(|process_info| unload_idle_model_server(process_info));
+        });
+    });
+}
+
+fn unload_idle_model_server(process_info: &ProcessInfo) {
+    let task_id = process_info.task_id.as_str();
+    tracing::info!(
+        "Unloading model server {} on task id: {} after idle timeout",
+        process_info.model_name,
+        task_id
+    );
+    let model_server = load_model_servers_config()
+        .model_servers
+        .into_iter()
+        .find(|model_server| model_server.task_id == task_id);
+    process_service::stop_process(task_id);
+    delete_model_servers_config(task_id);
+    if let Some(model_server) = model_server {
+        if model_server.args.start_on_demand {
+            let unloaded_model_servers =
+                UNLOADED_MODEL_SERVERS.get_or_init(|| init_unloaded_model_servers());
+            let mut map = unloaded_model_servers.lock().unwrap();
+            map.insert(model_server.args.model_name.clone(), model_server.args);
+        }
     }
-    validation
+    system_service::send_message(
+        MessageSource::ProcessService,
+        MessageType::ProcessUnloaded,
+        format!(
+            "Model server {} is unloaded after idle timeout on task id: {}",
+            process_info.model_name, task_id
+        ),
+    );
+}
+
+/// Find model server unloaded by idle timeout by model name or model id, so it can be started on demand
+pub fn get_unloaded_model_server(model: &str) -> Option<ModelServiceArgs> {
+    let unloaded_model_servers = UNLOADED_MODEL_SERVERS.get_or_init(|| init_unloaded_model_servers());
+    let map = unloaded_model_servers.lock().unwrap();
+    map.get(model).cloned().or_else(|| {
+        map.values()
+            .find(|args| args.model_id == model)
+            .cloned()
+    })
+}
+
+/// Forget unloaded model server once it is started again
+pub fn remove_unloaded_model_server(model_name: &str) {
+    let unloaded_model_servers = UNLOADED_MODEL_SERVERS.get_or_init(|| init_unloaded_model_servers());
+    let mut map = unloaded_model_servers.lock().unwrap();
+    map.remove(model_name);
 }
 
 async fn start_model_server_in_process(
@@ -397,7 +764,6 @@ async fn start_model_server_in_process(
     port: Option<String>,
     is_spawn_process: bool,
 ) -> Result<String, anyhow::Error> {
-    let new_port_number = synvek::inc_port_number();
     let config = Config::new();
     let model_dir = config.get_model_dir();
     let log_dir = config.get_log_dir();
@@ -406,28 +772,37 @@ async fn start_model_server_in_process(
     } else {
         Uuid::new_v4().to_string()
     };
-    let port = if port.is_some() {
-        port.unwrap()
-    } else {
-        new_port_number.to_string()
-    };
     let moved_task_id = task_id.clone();
-    let task = fetch_service::load_local_task(args.model_name.clone().as_str());
-    if task.is_none() {
+    let Some(backend) = backend_service::get_backend(args.backend.as_str()) else {
         // Need to terminate in multiprocess mode  right now.
         if is_spawn_process {
             panic!(
-                "Failed to start model server with reason: task not found, args = {:?} ",
+                "Failed to start model server with reason: unsupported backend, args = {:?} ",
                 args,
             );
         }
-        return Err(anyhow::anyhow!("Task not found"));
-    }
-    let task = task.unwrap();
-    let private_model = task.clone().private_model;
-    let backend = args.backend.clone();
-    let acceleration = args.acceleration.clone();
-    let validation = validate_acceleration(backend.as_str(), acceleration.as_str());
+        return Err(anyhow::anyhow!("Unsupported backend: {}", args.backend));
+    };
+    let task = fetch_service::load_local_task(args.model_name.clone().as_str());
+    let task = match task {
+        Some(task) => task,
+        None if !backend.requires_model() => Task {
+            task_name: args.model_name.clone(),
+            ..Default::default()
+        },
+        None => {
+            // Need to terminate in multiprocess mode  right now.
+            if is_spawn_process {
+                panic!(
+                    "Failed to start model server with reason: task not found, args = {:?} ",
+                    args,
+                );
+            }
+            return Err(anyhow::anyhow!("Task not found"));
+        }
+    };
+    let validation =
+        backend_service::validate_acceleration(args.backend.as_str(), args.acceleration.as_str());
     if !validation {
         // Need to terminate in multiprocess mode  right now.
         if is_spawn_process {
@@ -440,7 +815,31 @@ async fn start_model_server_in_process(
             "Unable to validate acceleration with backend"
         ));
     }
+    // Port is allocated after validation so it is not leaked on invalid request
+    let port = if port.is_some() {
+        port.unwrap()
+    } else {
+        synvek::allocate_port_number()?.to_string()
+    };
     tracing::info!("Starting model server on port {}", port);
+    // Model server running in this process is registered so it can be listed and stopped
+    let cancellation = if is_spawn_process {
+        None
+    } else {
+        let cancellation =
+            process_service::register_in_process_server(task_id.as_str(), port.as_str(), args);
+        if cancellation.is_none() {
+            if let Ok(port) = port.parse::<u16>() {
+                synvek::release_port_number(port);
+            }
+            return Err(anyhow::anyhow!("Model server is being stopped: {}", task_id));
+        }
+        if args.idle_timeout.is_some() {
+            start_idle_monitor();
+        }
+        cancellation
+    };
+    let cancellation_registered = cancellation.is_some();
     let args = args.clone();
     let _ = thread::spawn(move || {
         let rt = runtime::Builder::new_current_thread()
@@ -454,113 +853,172 @@ async fn start_model_server_in_process(
                 OsString::from("--port"),
                 OsString::from(port.clone()),
             ];
-            let mut selected_backend = common::BACKEND_UNKNOWN;
-            if backend == common::BACKEND_DEFAULT {
-                selected_backend = common::BACKEND_DEFAULT;
-            } else if backend == common::BACKEND_LLAMA_CPP {
-                selected_backend = common::BACKEND_LLAMA_CPP;
-            } else if backend == common::BACKEND_STABLE_DIFFUSION_CPP {
-                selected_backend = common::BACKEND_STABLE_DIFFUSION_CPP;
-            } else if backend == common::BACKEND_WHISPER_CPP {
-                selected_backend = common::BACKEND_WHISPER_CPP;
-            }
-            if private_model {
-                if selected_backend == common::BACKEND_DEFAULT {
-                    populate_args_private_with_backend_default(
-                        &task,
-                        model_dir,
-                        log_dir,
-                        &mut start_args,
-                    );
-                } else if selected_backend == common::BACKEND_LLAMA_CPP {
-                    populate_args_private_with_backend_llama_cpp(&task, model_dir, &mut start_args);
-                } else if selected_backend == common::BACKEND_STABLE_DIFFUSION_CPP {
-                    populate_args_private_with_backend_stable_diffusion_cpp(
-                        &task,
-                        model_dir,
-                        &mut start_args,
-                    );
-                } else if selected_backend == common::BACKEND_WHISPER_CPP {
-                } else {
-                }
-            } else if selected_backend == common::BACKEND_LLAMA_CPP {
-                populate_args_with_backend_llama_cpp(&args, &task, model_dir, &mut start_args);
-            } else if selected_backend == common::BACKEND_WHISPER_CPP {
-            } else if selected_backend == common::BACKEND_STABLE_DIFFUSION_CPP {
-                populate_args_with_backend_stable_diffusion_cpp(
-                    &args,
-                    &task,
-                    model_dir,
-                    &mut start_args,
-                );
-            } else if selected_backend == common::BACKEND_DEFAULT {
-                populate_args_with_backend_default(
-                    &args.clone(),
-                    &task,
-                    model_dir,
-                    log_dir,
-                    &mut start_args,
-                );
-            }
+            let path = args.path.clone().unwrap_or_default();
+            let context = BackendContext {
+                args: &args,
+                task: &task,
+                model_dir,
+                log_dir,
+                task_id: &task_id,
+                port: &port,
+                path: &path,
+                is_spawn_process,
+            };
+            backend.populate_args(&context, &mut start_args);
             tracing::info!("Starting model server {:?}", start_args);
-            let path = args.path.clone();
-            let path = if path.is_some() {
-                path.unwrap()
-            } else {
-                "".to_string()
+            let Some(cancellation) = cancellation else {
+                backend.start(&context, &start_args).await;
+                return;
             };
-            if selected_backend == common::BACKEND_DEFAULT {
-                // start_mistral_server(args, &start_args, task_id, port, path, is_spawn_process)
-                //     .await;
-                start_mistral_server_dll(
-                    &args,
-                    &start_args,
-                    &task_id,
-                    &port,
-                    &path,
-                    is_spawn_process,
-                )
-                .await;
-            } else if selected_backend == common::BACKEND_LLAMA_CPP {
-                start_llama_cpp_server(
-                    &args,
-                    &start_args,
-                    &task_id,
-                    &port,
-                    &path,
-                    is_spawn_process,
-                )
-                .await;
-            } else if selected_backend == common::BACKEND_STABLE_DIFFUSION_CPP {
-                start_stable_diffusion_cpp_server(
-                    &args,
-                    &start_args,
-                    &task_id,
-                    &port,
-                    &path,
-                    is_spawn_process,
-                )
-                .await;
-            } else if selected_backend == common::BACKEND_WHISPER_CPP {
-                tracing::error!("Unimplemented backend detected: {}", selected_backend);
-            } else {
-                tracing::error!("Unsupported backend detected: {}", selected_backend);
+            // Model server is dropped on stop request, stop is rejected for backend blocking in
+            // library call since it can't be interrupted.
+            tokio::select! {
+                _ = backend.start(&context, &start_args) => {
+                    tracing::info!("Model server exited on task id: {}", task_id);
+                }
+                _ = cancellation => {
+                    tracing::info!("Model server is cancelled on task id: {}", task_id);
+                }
             }
         });
+        if cancellation_registered {
+            process_service::finish_in_process_server(task_id.as_str());
+        }
     });
     Ok(moved_task_id.clone())
 }
 
-pub fn stop_model_server(task_id: &str) {
+pub fn stop_model_server(task_id: &str) -> anyhow::Result<()> {
     let synvek_config = config::get_synvek_config();
     if synvek_config.multi_process {
         process_service::stop_process(task_id.clone());
+        delete_model_servers_config(task_id);
     } else {
-        //mistralrs_server::stop_server(task_id);
+        let process_info = process_service::get_processes()
+            .into_iter()
+            .find(|process_info| process_info.task_id == task_id);
+        if let Some(process_info) = process_info {
+            if !can_stop_model_server(&process_info) {
+                return Err(anyhow::anyhow!(
+                    "Model server of backend {} can't be stopped in single process mode, restart service to stop it",
+                    process_info.backend
+                ));
+            }
+        }
+        process_service::stop_process(task_id);
+    }
+    Ok(())
+}
+
+/// Whether model server can be stopped, model server running in this process is stopped only if
+/// its backend returns on cancellation.
+fn can_stop_model_server(process_info: &ProcessInfo) -> bool {
+    match process_info.process_type {
+        ProcessType::ModelServer => true,
+        ProcessType::InProcess => backend_service::get_backend(process_info.backend.as_str())
+            .map(|backend| backend.can_stop_in_process())
+            .unwrap_or(true),
+    }
+}
+
+pub fn load_model_servers_config() -> ModelServerConfigs {
+    let config = Config::new();
+    let mut model_servers_config = config.get_config_dir();
+    model_servers_config.push(common::MODEL_SERVERS_FILE);
+    let data_result = fs::read_to_string(model_servers_config.clone());
+    if let Ok(data) = data_result {
+        let model_servers_result = serde_json::from_str(&data);
+        if let Ok(model_servers) = model_servers_result {
+            return model_servers;
+        } else {
+            tracing::error!(
+                "Invalid model servers config file: {}",
+                model_servers_config.display()
+            );
+        }
     }
+    ModelServerConfigs::default()
 }
 
-fn populate_args_private_with_backend_default(
+fn write_model_servers_config(model_servers: &ModelServerConfigs) {
+    let config = Config::new();
+    let mut model_servers_config = config.get_config_dir();
+    model_servers_config.push(common::MODEL_SERVERS_FILE);
+    let json = serde_json::to_string_pretty(model_servers).unwrap();
+    let write_result = fs::write(model_servers_config.clone(), json);
+    if let Err(e) = write_result {
+        tracing::error!(
+            "Failed to write model servers config file: {} with error: {}",
+            model_servers_config.display(),
+            e
+        );
+    }
+}
+
+pub fn update_model_servers_config(task_id: &str, args: &ModelServiceArgs) {
+    let _lock = MODEL_SERVERS_CONFIG_LOCK.lock().unwrap();
+    let mut model_servers = load_model_servers_config();
+    let model_server = ModelServerConfig {
+        task_id: task_id.to_string(),
+        args: args.clone(),
+    };
+    let index = model_servers
+        .model_servers
+        .iter()
+        .position(|element| element.task_id == task_id);
+    if let Some(index) = index {
+        model_servers.model_servers[index] = model_server;
+    } else {
+        model_servers.model_servers.push(model_server);
+    }
+    write_model_servers_config(&model_servers);
+}
+
+pub fn delete_model_servers_config(task_id: &str) {
+    let _lock = MODEL_SERVERS_CONFIG_LOCK.lock().unwrap();
+    let mut model_servers = load_model_servers_config();
+    model_servers
+        .model_servers
+        .retain(|element| element.task_id != task_id);
+    write_model_servers_config(&model_servers);
+}
+
+/// Restart model servers which were running before service restart and have autostart turned on.
+/// Model servers without autostart are dropped since they are not running anymore. Model servers
+/// failed to restart are kept in config, so they are restored again on next service start.
+pub async fn restore_model_servers() {
+    let synvek_config = config::get_synvek_config();
+    if !synvek_config.multi_process {
+        return;
+    }
+    let model_servers = {
+        let _lock = MODEL_SERVERS_CONFIG_LOCK.lock().unwrap();
+        load_model_servers_config()
+    };
+    for model_server in model_servers.model_servers.iter() {
+        if !model_server.args.autostart {
+            delete_model_servers_config(model_server.task_id.as_str());
+            continue;
+        }
+        tracing::info!(
+            "Restoring model server {} of previous task {}",
+            model_server.args.model_name,
+            model_server.task_id
+        );
+        let start_result = start_model_server_from_web(true, &model_server.args).await;
+        match start_result {
+            // Restarted model server is saved with its new task id
+            Ok(_) => delete_model_servers_config(model_server.task_id.as_str()),
+            Err(e) => tracing::error!(
+                "Failed to restore model server {} with error: {}, it is kept for next service start",
+                model_server.args.model_name,
+                e
+            ),
+        }
+    }
+}
+
+pub(crate) fn populate_args_private_with_backend_default(
     task: &Task,
     model_dir: PathBuf,
     log_dir: PathBuf,
@@ -591,7 +1049,8 @@ fn populate_args_private_with_backend_default(
     }
 }
 
-fn populate_args_private_with_backend_llama_cpp(
+pub(crate) fn populate_args_private_with_backend_llama_cpp(
+    args: &ModelServiceArgs,
     task: &Task,
     model_dir: PathBuf,
     start_args: &mut Vec<OsString>,
@@ -605,15 +1064,101 @@ fn populate_args_private_with_backend_llama_cpp(
         start_args.push(OsString::from("-m"));
         start_args.push(OsString::from(model_path.clone()));
     }
-    start_args.push(OsString::from("--jinja"));
+    populate_serving_args_with_backend_llama_cpp(args, task, start_args);
+    populate_lora_args_with_backend_llama_cpp(args, start_args);
     populate_log_file_args_with_backend_llama_cpp(start_args)
 }
 
+/// Embedding and rerank models are served without chat endpoints, chat model is served with jinja
+/// chat template.
+fn populate_serving_args_with_backend_llama_cpp(
+    args: &ModelServiceArgs,
+    task: &Task,
+    start_args: &mut Vec<OsString>,
+) {
+    if args.model_type == common::MODEL_TYPE_EMBEDDING {
+        start_args.push(OsString::from("--embedding"));
+    } else if args.model_type == common::MODEL_TYPE_RERANK {
+        start_args.push(OsString::from("--reranking"));
+    } else {
+        start_args.push(OsString::from("--jinja"));
+        populate_chat_template_args_with_backend_llama_cpp(task, start_args);
+    }
+    if let Some(pooling) = args.pooling.as_ref() {
+        start_args.push(OsString::from("--pooling"));
+        start_args.push(OsString::from(pooling));
+    }
+}
+
+/// Chat template attached to task overrides template embedded in model
+fn populate_chat_template_args_with_backend_llama_cpp(task: &Task, start_args: &mut Vec<OsString>) {
+    if let Some(chat_template_path) =
+        template_service::get_task_chat_template_path(task.task_name.as_str())
+    {
+        start_args.push(OsString::from("--chat-template-file"));
+        start_args.push(OsString::from(chat_template_path));
+    }
+}
+
 fn populate_log_file_args_with_backend_llama_cpp(start_args: &mut Vec<OsString>) {
     //start_args.push(OsString::from("--log-timestamps"));
 }
 
-fn populate_args_private_with_backend_stable_diffusion_cpp(
+/// Find adapter file of lora task, its file is linked into lora dir once it is downloaded. Private
+/// lora model is file in lora dir already.
+pub fn get_lora_adapter_path(name: &str) -> anyhow::Result<PathBuf> {
+    let task = fetch_service::load_local_tasks(true)
+        .tasks
+        .into_iter()
+        .find(|task| task.task_name == name && (task.lora_model || task.private_lora_model))
+        .ok_or(anyhow::anyhow!("LoRA model not found: {}", name))?;
+    let file_name = if task.private_lora_model {
+        Some(task.task_name.clone())
+    } else {
+        task.task_items
+            .iter()
+            .find(|item| item.file_name.to_uppercase().ends_with(".GGUF"))
+            .map(|item| item.file_name.clone())
+    };
+    let Some(file_name) = file_name else {
+        return Err(anyhow::anyhow!("No GGUF found in LoRA model: {}", name));
+    };
+    let lora_path = config::get_lora_dir().join(file_name);
+    if !lora_path.exists() {
+        return Err(anyhow::anyhow!(
+            "LoRA model is not downloaded: {}",
+            lora_path.display()
+        ));
+    }
+    Ok(lora_path)
+}
+
+fn populate_lora_args_with_backend_llama_cpp(
+    args: &ModelServiceArgs,
+    start_args: &mut Vec<OsString>,
+) {
+    let Some(lora_adapters) = args.lora_adapters.as_ref() else {
+        return;
+    };
+    lora_adapters.iter().for_each(|lora_adapter| {
+        match get_lora_adapter_path(lora_adapter.name.as_str()) {
+            Ok(lora_path) => match lora_adapter.scale {
+                Some(scale) if scale != 1.0 => {
+                    start_args.push(OsString::from("--lora-scaled"));
+                    start_args.push(OsString::from(lora_path));
+                    start_args.push(OsString::from(scale.to_string()));
+                }
+                _ => {
+                    start_args.push(OsString::from("--lora"));
+                    start_args.push(OsString::from(lora_path));
+                }
+            },
+            Err(e) => tracing::error!("Failed to find LoRA adapter with error: {}", e),
+        }
+    });
+}
+
+pub(crate) fn populate_args_private_with_backend_stable_diffusion_cpp(
     task: &Task,
     model_dir: PathBuf,
     start_args: &mut Vec<OsString>,
@@ -629,7 +1174,7 @@ fn populate_args_private_with_backend_stable_diffusion_cpp(
     }
 }
 
-fn populate_args_with_backend_default(
+pub(crate) fn populate_args_with_backend_default(
     args: &ModelServiceArgs,
     task: &Task,
     model_dir: PathBuf,
@@ -699,8 +1244,12 @@ fn populate_args_with_backend_default(
             start_args.push(OsString::from("flux"));
         }
     }
-    // start_args.push(OsString::from("--jinja-explicit"));
-    // start_args.push(OsString::from("chat_template/mistral_nemo_tool_call.jinja"));
+    if let Some(chat_template_path) =
+        template_service::get_task_chat_template_path(task.task_name.as_str())
+    {
+        start_args.push(OsString::from("--jinja-explicit"));
+        start_args.push(OsString::from(chat_template_path));
+    }
     if args.model_type.eq("speech") {
         start_args.push(OsString::from("-a"));
         start_args.push(OsString::from("dia"));
@@ -754,7 +1303,51 @@ fn get_model_id_path(model_source: &str, model_dir: PathBuf, model_id: &str) ->
     }
 }
 
-fn populate_args_with_backend_llama_cpp(
+/// Path of downloaded file of task item in model cache
+fn get_task_item_path(task: &Task, item: &TaskItem, model_dir: PathBuf) -> PathBuf {
+    let mut model_path = model_dir;
+    let mut model_dir_name = "models--".to_owned() + item.repo_name.replace("/", "--").as_str();
+    if task.model_source == MODEL_SOURCE_MODELSCOPE {
+        model_dir_name = model_dir_name.replace("models--", "modelscope-models--");
+    }
+    model_path.push(model_dir_name);
+    model_path.push("snapshots");
+    model_path.push(item.commit_hash.clone());
+    model_path.push(item.file_name.clone());
+    model_path
+}
+
+/// GGUF file of task which is loaded by llama.cpp, it is the first file of task
+pub fn get_task_gguf_file(task: &Task) -> Option<PathBuf> {
+    let config = Config::new();
+    let model_dir = config.get_model_dir();
+    if task.private_model {
+        if task.task_name.to_uppercase().ends_with(".GGUF") {
+            let mut model_path = model_dir;
+            model_path.push(task.task_name.clone());
+            return Some(model_path);
+        }
+        return None;
+    }
+    let item = task.task_items.first()?;
+    if !item.file_name.to_uppercase().ends_with(".GGUF") {
+        return None;
+    }
+    Some(get_task_item_path(task, item, model_dir))
+}
+
+/// Read model facts from GGUF file of task
+pub fn inspect_model(task_name: &str) -> Result<GgufInfo, anyhow::Error> {
+    let Some(task) = fetch_service::load_local_task(task_name) else {
+        return Err(anyhow::anyhow!("Task not found: {}", task_name));
+    };
+    let Some(gguf_file) = get_task_gguf_file(&task) else {
+        return Err(anyhow::anyhow!("No GGUF file found in task: {}", task_name));
+    };
+    gguf_helper::read_gguf_info(gguf_file)
+}
+
+pub(crate) fn populate_args_with_backend_llama_cpp(
     args: &ModelServiceArgs,
     task: &Task,
     model_dir: PathBuf,
@@ -764,16 +1357,7 @@ fn populate_args_with_backend_llama_cpp(
     let model_type = args.model_type.as_str();
     task.task_items.iter().enumerate().for_each(|(index, item)| {
         let uniform_name = item.file_name.to_uppercase();
-        let mut model_path = model_dir.clone();
-        let mut model_dir_name =
-            "models--".to_owned() + item.repo_name.replace("/", "--").as_str();
-        if task.model_source == MODEL_SOURCE_MODELSCOPE {
-            model_dir_name = model_dir_name.replace("models--", "modelscope-models--");
-        }
-        model_path.push(model_dir_name);
-        model_path.push("snapshots");
-        model_path.push(item.commit_hash.clone());
-        model_path.push(item.file_name.clone());
+        let model_path = get_task_item_path(task, item, model_dir.clone());
         if index == 0 &&  uniform_name.ends_with(".GGUF") {
             gguf_found = true;
             start_args.push(OsString::from("-m"));
@@ -784,45 +1368,97 @@ fn populate_args_with_backend_llama_cpp(
             start_args.push(OsString::from("--mmproj"));
             start_args.push(OsString::from(model_path.to_str().unwrap() ));
         }
-        start_args.push(OsString::from("--jinja"));
-
-        if let Some(context_length) = args.context_length {
-            start_args.push(OsString::from("--ctx-size"));
-            start_args.push(OsString::from(context_length.to_string()));
-        }
-        if let Some(gpu_layers) = args.gpu_layers {
-            start_args.push(OsString::from("--gpu-layers"));
-            start_args.push(OsString::from(gpu_layers.to_string()));
-        }
-        if let Some(cpu_threads) = args.cpu_threads {
-            start_args.push(OsString::from("--threads"));
-            start_args.push(OsString::from(cpu_threads.to_string()));
-        }
-        if let Some(batch_size) = args.batch_size {
-            start_args.push(OsString::from("--batch-size"));
-            start_args.push(OsString::from(batch_size.to_string()));
-        }
-        if let Some(rope_scaling) = args.rope_scaling.clone() {
-            start_args.push(OsString::from("--rope-scaling"));
-            start_args.push(OsString::from(rope_scaling.as_str()));
-        }
-        if let Some(rope_scale) = args.rope_scale {
-            start_args.push(OsString::from("--rope-scale"));
-            start_args.push(OsString::from(rope_scale.to_string()));
-        }
-        if let Some(rope_freq_base) = args.rope_freq_base {
-            start_args.push(OsString::from("--rope-freq-base"));
-            start_args.push(OsString::from(rope_freq_base.to_string()));
-        }
-
     });
+    populate_serving_args_with_backend_llama_cpp(args, task, start_args);
+    if let Some(context_length) = args.context_length {
+        start_args.push(OsString::from("--ctx-size"));
+        start_args.push(OsString::from(context_length.to_string()));
+    }
+    if let Some(gpu_layers) = args.gpu_layers {
+        start_args.push(OsString::from("--gpu-layers"));
+        start_args.push(OsString::from(gpu_layers.to_string()));
+    }
+    if let Some(cpu_threads) = args.cpu_threads {
+        start_args.push(OsString::from("--threads"));
+        start_args.push(OsString::from(cpu_threads.to_string()));
+    }
+    if let Some(batch_size) = args.batch_size {
+        start_args.push(OsString::from("--batch-size"));
+        start_args.push(OsString::from(batch_size.to_string()));
+    }
+    if let Some(rope_scaling) = args.rope_scaling.clone() {
+        start_args.push(OsString::from("--rope-scaling"));
+        start_args.push(OsString::from(rope_scaling.as_str()));
+    }
+    if let Some(rope_scale) = args.rope_scale {
+        start_args.push(OsString::from("--rope-scale"));
+        start_args.push(OsString::from(rope_scale.to_string()));
+    }
+    if let Some(rope_freq_base) = args.rope_freq_base {
+        start_args.push(OsString::from("--rope-freq-base"));
+        start_args.push(OsString::from(rope_freq_base.to_string()));
+    }
     if !gguf_found {
         tracing::error!("No GGUF found");
     }
+    populate_lora_args_with_backend_llama_cpp(args, start_args);
     populate_log_file_args_with_backend_llama_cpp(start_args)
 }
 
-fn populate_args_with_backend_stable_diffusion_cpp(
+/// Whisper model is a single ggml file, transcription is served on OpenAI compatible path
+pub(crate) fn populate_args_with_backend_whisper_cpp(
+    args: &ModelServiceArgs,
+    task: &Task,
+    model_dir: PathBuf,
+    start_args: &mut Vec<OsString>,
+) {
+    let item = task
+        .task_items
+        .iter()
+        .find(|item| item.file_name.to_uppercase().ends_with(".BIN"))
+        .or(task.task_items.first());
+    if let Some(item) = item {
+        let model_path = get_task_item_path(task, item, model_dir);
+        start_args.push(OsString::from("-m"));
+        start_args.push(OsString::from(model_path));
+    } else {
+        tracing::error!("No whisper model found");
+    }
+    populate_common_args_with_backend_whisper_cpp(args, start_args);
+}
+
+pub(crate) fn populate_args_private_with_backend_whisper_cpp(
+    args: &ModelServiceArgs,
+    task: &Task,
+    model_dir: PathBuf,
+    start_args: &mut Vec<OsString>,
+) {
+    let mut model_path = model_dir.clone();
+    model_path.push(task.task_name.clone());
+    start_args.push(OsString::from("-m"));
+    start_args.push(OsString::from(model_path));
+    populate_common_args_with_backend_whisper_cpp(args, start_args);
+}
+
+fn populate_common_args_with_backend_whisper_cpp(
+    args: &ModelServiceArgs,
+    start_args: &mut Vec<OsString>,
+) {
+    start_args.push(OsString::from("--inference-path"));
+    start_args.push(OsString::from(common::WHISPER_INFERENCE_PATH));
+    // Language is detected unless it is provided in request
+    start_args.push(OsString::from("--language"));
+    start_args.push(OsString::from("auto"));
+    if args.cpu || args.acceleration == common::ACCELERATION_CPU {
+        start_args.push(OsString::from("--no-gpu"));
+    }
+    if let Some(cpu_threads) = args.cpu_threads {
+        start_args.push(OsString::from("--threads"));
+        start_args.push(OsString::from(cpu_threads.to_string()));
+    }
+}
+
+pub(crate) fn populate_args_with_backend_stable_diffusion_cpp(
     args: &ModelServiceArgs,
     task: &Task,
     model_dir: PathBuf,
@@ -1050,7 +1686,7 @@ async fn start_mistral_server(
     // }
 }
 
-async fn start_mistral_server_dll(
+pub(crate) async fn start_mistral_server_dll(
     args: &ModelServiceArgs,
     start_args: &Vec<OsString>,
     task_id: &str,
@@ -1062,10 +1698,16 @@ async fn start_mistral_server_dll(
         model_name: args.model_name.clone(),
         task_id: task_id.to_string(),
         started: false,
+        state: ProcessState::Spawning,
+        state_reason: None,
+        state_time: 0,
+        state_history: vec![],
         port: port.to_string(),
         isq: args.isq.clone(),
         model_id: args.model_id.clone().to_string(),
         model_type: args.model_type.clone().to_string(),
+        capability: get_model_capability(args.backend.as_str(), args.model_type.as_str())
+            .to_string(),
         path: path.to_string(),
         token_source: args.token_source.clone(),
         cpu: args.cpu,
@@ -1079,6 +1721,25 @@ async fn start_mistral_server_dll(
         rope_scaling: args.rope_scaling.clone(),
         rope_scale: args.rope_scale.clone(),
         rope_freq_base: args.rope_freq_base.clone(),
+        autostart: args.autostart,
+        restart_policy: args
+            .restart_policy
+            .clone()
+            .unwrap_or(common::RESTART_POLICY_NEVER.to_string()),
+        restart_count: 0,
+        last_exit_status: None,
+        idle_timeout: args.idle_timeout,
+        last_activity_time: 0,
+        replica_group: args.replica_group.clone(),
+        load_balancing: args
+            .load_balancing
+            .clone()
+            .unwrap_or(common::LOAD_BALANCING_ROUND_ROBIN.to_string()),
+        active_requests: 0,
+        max_concurrency: args.max_concurrency,
+        max_queue_size: args.max_queue_size,
+        queue_timeout: args.queue_timeout,
+        queued_requests: 0,
     };
     let config = Config::new();
     let main_process_port = config.get_config_port().to_string();
@@ -1228,7 +1889,7 @@ LOG_LEVEL_INFO  = 2,
 LOG_LEVEL_WARN  = 3,
 LOG_LEVEL_ERROR = 4,
 **/
-extern "C" fn handle_llama_cpp_log_callback(log_level: i32, c_msg: *const c_char) {
+pub(crate) extern "C" fn handle_llama_cpp_log_callback(log_level: i32, c_msg: *const c_char) {
     let result = panic::catch_unwind(AssertUnwindSafe(|| {
         let c_msg_ptr = unsafe { c_msg.as_ref() };
         let rust_msg = match c_msg_ptr {
@@ -1256,80 +1917,42 @@ extern "C" fn handle_llama_cpp_log_callback(log_level: i32, c_msg: *const c_char
     }
 }
 
-async fn start_llama_cpp_server(
-    args: &ModelServiceArgs,
-    start_args: &Vec<OsString>,
-    task_id: &str,
-    port: &str,
-    path: &str,
-    is_spawn_process: bool,
-) {
-    let base_lib_name = "synvek_backend_llama";
-    let acceleration = args.acceleration.clone();
-    let lib_name = utils::get_load_library_name(base_lib_name, acceleration.as_str());
-    let lib_name = utils::get_backend_path(lib_name.as_str());
-
-    unsafe {
-        tracing::info!("Search Llama server with name: {}", lib_name.clone());
-        let lib = Library::new(lib_name);
-        if let Ok(lib) = lib {
-            tracing::info!("Loading Llama server...");
-            let start_llama_server_func = lib.get(b"start_llama_server");
-            let init_log_callback_func = lib.get(b"init_log_callback");
-            let cleanup_log_callback_func = lib.get(b"cleanup_log_callback");
-            match (
-                start_llama_server_func,
-                init_log_callback_func,
-                cleanup_log_callback_func,
-            ) {
-                (
-                    Ok(start_llama_server_func),
-                    Ok(init_log_callback_func),
-                    Ok(cleanup_log_callback_func),
-                ) => {
-                    let start_llama_server: Symbol<StartLlamaServer> = start_llama_server_func;
-                    let init_log_callback: Symbol<InitLogCallback> = init_log_callback_func;
-                    let cleanup_log_callback: Symbol<CleanupLogCallback> = cleanup_log_callback_func;
-                    let c_strings = start_args
-                        .iter()
-                        .map(|s| CString::new(s.to_str().unwrap()))
-                        .collect::<anyhow::Result<Vec<_>, _>>();
-                    if let Ok(c_strings) = c_strings {
-                        let raw_ptrs: Vec<*const c_char> =
-                            c_strings.iter().map(|cs| cs.as_ptr()).collect();
-                        tracing::info!("Starting Llama server...");
-                        start_server_monitor(task_id.to_string(), port.to_string());
-                        init_log_callback(Some(handle_llama_cpp_log_callback));
-                        let result = start_llama_server(raw_ptrs.len() as c_int, raw_ptrs.as_ptr());
-                        cleanup_log_callback();
-                        tracing::info!("Llama server exited with result: {}", result);
-                    }
+/**
+For callback from c/c++
+LOG_LEVEL_DEBUG = 1,
+LOG_LEVEL_INFO  = 2,
+LOG_LEVEL_WARN  = 3,
+LOG_LEVEL_ERROR = 4,
+**/
+pub(crate) extern "C" fn handle_whisper_cpp_log_callback(log_level: i32, c_msg: *const c_char) {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let c_msg_ptr = unsafe { c_msg.as_ref() };
+        match c_msg_ptr {
+            Some(ptr) => unsafe {
+                let mut log_message = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+                if log_message.len() > 0 && log_message.as_bytes()[log_message.len() - 1] == b'\n' {
+                    log_message.pop();
                 }
-                _ => {
-                    tracing::error!("Failed to load functions of backend Llama server.");
-                    // Need to terminate in multiprocess mode  right now.
-                    if is_spawn_process {
-                        panic!(
-                            "Failed to load functions of backend Llama server, args = {:?} ",
-                            args.clone(),
-                        );
-                    }
+                match log_level {
+                    1 => tracing::debug!(target: "backend:whisper.cpp", "{}", log_message),
+                    2 => tracing::info!(target: "backend:whisper.cpp", "{}", log_message),
+                    3 => tracing::warn!(target: "backend:whisper.cpp", "{}", log_message),
+                    4 => tracing::error!(target: "backend:whisper.cpp", "{}", log_message),
+                    _ => tracing::error!(target: "backend:whisper.cpp", "{}", log_message),
                 }
+            },
+            None => {
+                tracing::error!(target: "backend:whisper.cpp", "Received null message from log callback");
             }
-        } else {
-            tracing::error!("Failed to load backend Llama server with error {:}.", lib.unwrap_err());
-            // Need to terminate in multiprocess mode  right now.
-            if is_spawn_process {
-                panic!(
-                    "Failed to load backend Llama server, args = {:?} ",
-                    args.clone(),
-                );
-            }
-        }
+        };
+    }));
+
+    if let Err(_) = result {
+        tracing::error!(target: "backend:whisper.cpp", "A panic occurred inside the log callback!");
     }
 }
 
-async fn start_stable_diffusion_cpp_server(
+pub(crate) async fn start_stable_diffusion_cpp_server(
     args: &ModelServiceArgs,
     start_args: &Vec<OsString>,
     task_id: &str,
@@ -1360,7 +1983,15 @@ async fn start_stable_diffusion_cpp_server(
     }
 }
 
-fn start_server_monitor(task_id: String, task_port: String) {
+/// Whether model server running in this process is stopped, model process exits with its monitor
+fn is_in_process_server_stopped(task_id: &str) -> bool {
+    !config::get_synvek_config().multi_process && !process_service::has_process(task_id)
+}
+
+/// Probe health of model server and report its state to main process. Server is loading weights
+/// while it answers health check with error, and it turns unhealthy if health check fails after
+/// it is ready.
+pub(crate) fn start_server_monitor(task_id: String, task_port: String, health_path: String) {
     let _ = thread::spawn(move || {
         let rt = runtime::Builder::new_current_thread()
             .enable_all()
@@ -1371,9 +2002,10 @@ fn start_server_monitor(task_id: String, task_port: String) {
             if let Ok(client) = client {
                 let mut server_process_address = "http://127.0.0.1:".to_string();
                 server_process_address.push_str(task_port.as_str());
-                server_process_address.push_str("/health");
+                server_process_address.push_str(health_path.as_str());
                 tracing::info!("Check if server ready to serve on: {}", server_process_address.clone());
 
+                let mut state = ProcessState::Spawning;
                 let mut counter = 0;
                 while counter < common::HEALTH_CHECK_COUNT {
                     let response = client
@@ -1386,6 +2018,7 @@ fn start_server_monitor(task_id: String, task_port: String) {
                             let response_data = response.text().await;
                             if let Ok(response_data) = response_data {
                                 tracing::info!("Server is ready to serve task_id: {} and response: {:?}",task_id.clone(), response_data );
+                                state = ProcessState::Ready;
                                 let _ = notify_main_process(task_id.as_str()).await;
                                 break;
                             } else {
@@ -1394,6 +2027,11 @@ fn start_server_monitor(task_id: String, task_port: String) {
                             }
                         } else {
                             tracing::error!( "Failed to check server process with task_id: {} and response: {:?}", task_id, response);
+                            // Server is up but still loading model
+                            if state == ProcessState::Spawning {
+                                state = ProcessState::LoadingWeights;
+                                let _ = notify_main_process_state(task_id.as_str(), state.clone(), None).await;
+                            }
                         }
                     } else {
                         tracing::error!( "Failed to check server process with task_id: {} and reason: {}", task_id, response.unwrap_err() );
@@ -1402,6 +2040,34 @@ fn start_server_monitor(task_id: String, task_port: String) {
                     let sleep_time = if counter < 60 { 1000} else {3000};
                     tokio::time::sleep(Duration::from_millis(sleep_time)).await;
                 }
+                if state != ProcessState::Ready {
+                    let reason = "Health check timed out".to_string();
+                    tracing::error!("Server failed to be ready on task_id: {} with reason: {}", task_id, reason);
+                    let _ = notify_main_process_state(task_id.as_str(), ProcessState::Failed, Some(reason)).await;
+                    return;
+                }
+                loop {
+                    tokio::time::sleep(Duration::from_secs(common::HEALTH_PROBE_INTERVAL_SECS)).await;
+                    if is_in_process_server_stopped(task_id.as_str()) {
+                        tracing::info!("Stop monitoring server on task_id: {}", task_id);
+                        break;
+                    }
+                    let response = client
+                        .get(server_process_address.clone())
+                        .header(header::CONTENT_TYPE, "application/json")
+                        .send()
+                        .await;
+                    let (new_state, reason) = match response {
+                        Ok(response) if response.status().is_success() => (ProcessState::Ready, None),
+                        Ok(response) => (ProcessState::Unhealthy, Some(format!("Health check returned {}", response.status()))),
+                        Err(e) => (ProcessState::Unhealthy, Some(e.to_string())),
+                    };
+                    if new_state != state {
+                        tracing::warn!("Server state changed to {:?} on task_id: {} with reason: {:?}", new_state, task_id, reason);
+                        state = new_state;
+                        let _ = notify_main_process_state(task_id.as_str(), state.clone(), reason).await;
+                    }
+                }
             }
         });
     });
@@ -1485,7 +2151,7 @@ async fn start_stable_diffusion_cpp_server_ex(
                         let raw_ptrs: Vec<*const c_char> =
                             c_strings.iter().map(|cs| cs.as_ptr()).collect();
                         tracing::info!("Starting Stable diffusion server...");
-                        start_server_monitor(task_id.to_string(), port.to_string());
+                        start_server_monitor(task_id.to_string(), port.to_string(), "/health".to_string());
                         init_log_callback(Some(handle_stable_diffusion_cpp_log_callback));
                         let result = start_sd_server(raw_ptrs.len() as c_int, raw_ptrs.as_ptr());
                         cleanup_log_callback();
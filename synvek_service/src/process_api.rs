@@ -2,11 +2,20 @@ use actix_web::{web, App, HttpResponse, HttpServer, Responder};
 use actix_web::{get, post, HttpRequest};
 use serde::{Deserialize, Serialize};
 use crate::process_service;
+use crate::process_service::ProcessState;
 
 /// Request for Start Model Server
 #[derive(Debug, Deserialize, Serialize)]
 pub struct HeartTickRequest {
     pub task_id: String,
+
+    /// State of model server, it is ready if not provided
+    #[serde(default)]
+    pub state: Option<ProcessState>,
+
+    /// Reason of state
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
 /// Response for Start Model Server
@@ -30,7 +39,8 @@ pub struct HeartTickResponse {
 #[post("/process/heart-tick")]
 async fn heart_tick(req: web::Json<HeartTickRequest>) -> impl Responder {
     let task_id = req.task_id.clone();
-    process_service::notify_process_running(task_id.as_str());
+    let state = req.state.clone().unwrap_or(ProcessState::Ready);
+    process_service::update_process_state(task_id.as_str(), state, req.reason.clone());
     let response = HeartTickResponse {
         success: true,
         code: "".to_string(),
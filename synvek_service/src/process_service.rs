@@ -3,14 +3,16 @@ use crate::process_api::HeartTickRequest;
 use crate::process_api::HeartTickResponse;
 use crate::script_service::stop_script;
 use crate::system_service::{MessageSource, MessageType};
-use crate::{config, system_service};
+use crate::{common, concurrency_service, config, model_log_service, model_service, resource_service};
+use crate::{synvek, system_service};
 use futures::executor;
 use reqwest::{Client, header};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::ffi::OsString;
 use std::fmt::Debug;
-use std::process::{Child, Command, Stdio};
-use std::sync::atomic::AtomicBool;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, SystemTime};
 use std::{env, thread};
@@ -20,6 +22,7 @@ use uuid::Uuid;
 use std::os::windows::process::CommandExt; // 注意 Windows 特有特性
 
 use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::oneshot;
 
 
 #[cfg(target_os = "windows")]
@@ -37,6 +40,51 @@ const CREATE_NEW_CONSOLE: u32 = 0x00000010;
 #[derive(Clone, Debug)]
 pub enum ProcessType {
     ModelServer,
+    /// Model server running on thread of this process in single process mode
+    InProcess,
+}
+
+/// Lifecycle state of model process
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessState {
+    /// Process is spawned and model server is not reachable yet
+    Spawning,
+    /// Model server is reachable but still loading model weights
+    LoadingWeights,
+    /// Model server passed health check and is ready to serve
+    Ready,
+    /// Model server was ready but health check is failing now
+    Unhealthy,
+    /// Process is asked to exit and waits for grace period
+    Stopping,
+    /// Process exited normally or by stop request
+    Stopped,
+    /// Process failed to start or exited unexpectedly, reason is recorded in transition
+    Failed,
+}
+
+/// Transition of model process to a state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessStateTransition {
+    pub state: ProcessState,
+
+    /// Reason of transition, e.g. exit status for failed state
+    pub reason: Option<String>,
+
+    /// Time of transition, in seconds
+    pub time: u64,
+}
+
+/// Content of ProcessStateChanged system message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessStateMessage {
+    #[serde(rename = "taskId")]
+    pub task_id: String,
+    #[serde(rename = "modelName")]
+    pub model_name: String,
+    pub state: ProcessState,
+    pub reason: Option<String>,
+    pub time: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -49,7 +97,11 @@ pub struct ProcessInfo {
 
     pub create_time: u64,
 
-    pub running: bool,
+    /// Current lifecycle state
+    pub state: ProcessState,
+
+    /// Recent state transitions with timestamps, oldest first
+    pub state_history: Vec<ProcessStateTransition>,
 
     pub model_name: String,
 
@@ -94,18 +146,238 @@ pub struct ProcessInfo {
 
     /// RoPE frequency base
     pub rope_freq_base: Option<u32>,
+
+    /// Restart model server automatically after service restart
+    pub autostart: bool,
+
+    /// Restart policy when process exits: never, on-failure or always
+    pub restart_policy: String,
+
+    /// Count of restarts
+    pub restart_count: u32,
+
+    /// Last exit status of restarted process
+    pub last_exit_status: Option<String>,
+
+    /// Idle timeout in seconds
+    pub idle_timeout: Option<u64>,
+
+    /// Last time a request is seen, in seconds
+    pub last_activity_time: u64,
+
+    /// Replica group which process belongs to
+    pub replica_group: Option<String>,
+
+    /// Routing across replicas: round-robin or least-busy
+    pub load_balancing: String,
+
+    /// Count of requests in flight through gateway, it is used for least-busy routing
+    pub active_requests: u32,
+
+    /// Requests served at same time
+    pub max_concurrency: Option<u32>,
+
+    /// Requests waiting in queue
+    pub max_queue_size: Option<u32>,
+
+    /// Seconds a request may wait in queue
+    pub queue_timeout: Option<u64>,
+}
+
+impl ProcessInfo {
+    /// Whether model server is ready to serve requests
+    pub fn is_ready(&self) -> bool {
+        self.state == ProcessState::Ready
+    }
+
+    /// Reason of latest state transition
+    pub fn state_reason(&self) -> Option<String> {
+        self.state_history
+            .last()
+            .and_then(|transition| transition.reason.clone())
+    }
+
+    /// Time of latest state transition, in seconds
+    pub fn state_time(&self) -> u64 {
+        self.state_history
+            .last()
+            .map(|transition| transition.time)
+            .unwrap_or(self.create_time)
+    }
 }
 
+/// Number of model processes which are not exited yet, it is used to wait for them at shutdown
+static ALIVE_PROCESSES: AtomicUsize = AtomicUsize::new(0);
+
 static GLOBAL_PROCESSES: OnceLock<Arc<Mutex<HashMap<String, ProcessInfo>>>> = OnceLock::new();
 
 fn init_processes() -> Arc<Mutex<HashMap<String, ProcessInfo>>> {
     Arc::new(Mutex::new(HashMap::new()))
 }
 
-fn insert_process(key: String, value: ProcessInfo) {
+/// Recently stopped or failed processes, oldest first, so their final state can still be queried
+static FINISHED_PROCESSES: OnceLock<Arc<Mutex<VecDeque<ProcessInfo>>>> = OnceLock::new();
+
+fn init_finished_processes() -> Arc<Mutex<VecDeque<ProcessInfo>>> {
+    Arc::new(Mutex::new(VecDeque::new()))
+}
+
+/// Cancellation handles of model servers running in this process, keyed by task id
+static GLOBAL_CANCELLATIONS: OnceLock<Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>> =
+    OnceLock::new();
+
+fn init_cancellations() -> Arc<Mutex<HashMap<String, oneshot::Sender<()>>>> {
+    Arc::new(Mutex::new(HashMap::new()))
+}
+
+/// Ask model server running in this process to stop, nothing is done for model processes
+fn cancel_in_process_server(task_id: &str) {
+    let cancellations = GLOBAL_CANCELLATIONS.get_or_init(|| init_cancellations());
+    let cancellation = cancellations.lock().unwrap().remove(task_id);
+    if let Some(cancellation) = cancellation {
+        let _ = cancellation.send(());
+    }
+}
+
+/// Register process in Spawning state unless it is being stopped, previous state transitions of
+/// task are kept so restarts can be followed.
+fn insert_process(key: String, mut value: ProcessInfo) -> bool {
     let process_map = GLOBAL_PROCESSES.get_or_init(|| init_processes());
     let mut map = process_map.lock().unwrap();
-    map.insert(key, value);
+    if let Some(process_info) = map.get(&key) {
+        if process_info.state == ProcessState::Stopping {
+            return false;
+        }
+        value.state = process_info.state.clone();
+        value.state_history = process_info.state_history.clone();
+    }
+    transition_process(&mut value, ProcessState::Spawning, None);
+    map.insert(key.clone(), value);
+    let finished_processes = FINISHED_PROCESSES.get_or_init(|| init_finished_processes());
+    finished_processes
+        .lock()
+        .unwrap()
+        .retain(|process_info| process_info.task_id != key);
+    true
+}
+
+fn get_now_time() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn trim_state_history(process_info: &mut ProcessInfo) {
+    let history_size = process_info.state_history.len();
+    if history_size > common::PROCESS_STATE_HISTORY_SIZE {
+        process_info
+            .state_history
+            .drain(0..history_size - common::PROCESS_STATE_HISTORY_SIZE);
+    }
+}
+
+/// Move process to new state, record transition and report it on system message stream
+fn transition_process(process_info: &mut ProcessInfo, state: ProcessState, reason: Option<String>) {
+    let time = get_now_time();
+    tracing::info!(
+        "Process state changed from {:?} to {:?} on task id: {} with reason: {:?}",
+        process_info.state,
+        state,
+        process_info.task_id,
+        reason
+    );
+    process_info.state = state.clone();
+    process_info.state_history.push(ProcessStateTransition {
+        state: state.clone(),
+        reason: reason.clone(),
+        time,
+    });
+    trim_state_history(process_info);
+    let message = ProcessStateMessage {
+        task_id: process_info.task_id.clone(),
+        model_name: process_info.model_name.clone(),
+        state,
+        reason,
+        time,
+    };
+    system_service::send_message(
+        MessageSource::ProcessService,
+        MessageType::ProcessStateChanged,
+        serde_json::to_string(&message).unwrap_or_default(),
+    );
+}
+
+fn create_process_info(
+    task_id: &str,
+    process_type: ProcessType,
+    port: &str,
+    model_args: &ModelServiceArgs,
+) -> ProcessInfo {
+    ProcessInfo {
+        task_id: task_id.to_string(),
+        process_id: "".to_string(),
+        process_type,
+        create_time: get_now_time(),
+        state: ProcessState::Spawning,
+        state_history: vec![],
+        model_name: model_args.model_name.clone(),
+        model_id: model_args.model_id.clone(),
+        model_type: model_args.model_type.clone(),
+        port: port.to_string(),
+        token_source: model_args.token_source.clone(),
+        isq: model_args.isq.clone(),
+        path: model_args.path.clone(),
+        cpu: model_args.cpu,
+        offloaded: model_args.offloaded,
+        backend: model_args.backend.clone(),
+        acceleration: model_args.acceleration.clone(),
+        context_length: model_args.context_length,
+        gpu_layers: model_args.gpu_layers,
+        cpu_threads: model_args.cpu_threads,
+        batch_size: model_args.batch_size,
+        rope_scaling: model_args.rope_scaling.clone(),
+        rope_scale: model_args.rope_scale,
+        rope_freq_base: model_args.rope_freq_base,
+        autostart: model_args.autostart,
+        restart_policy: model_args
+            .restart_policy
+            .clone()
+            .unwrap_or(common::RESTART_POLICY_NEVER.to_string()),
+        restart_count: 0,
+        last_exit_status: None,
+        idle_timeout: model_args.idle_timeout,
+        last_activity_time: get_now_time(),
+        replica_group: model_args.replica_group.clone(),
+        load_balancing: model_args
+            .load_balancing
+            .clone()
+            .unwrap_or(common::LOAD_BALANCING_ROUND_ROBIN.to_string()),
+        active_requests: 0,
+        max_concurrency: model_args.max_concurrency,
+        max_queue_size: model_args.max_queue_size,
+        queue_timeout: model_args.queue_timeout,
+    }
+}
+
+/// Record final state of process and move it to recently finished processes
+fn finish_process(task_id: &str, state: ProcessState, reason: Option<String>) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    if let Some(mut process_info) = map.remove(task_id) {
+        transition_process(&mut process_info, state, reason);
+        release_process_port(&process_info);
+        concurrency_service::remove_limiter(task_id);
+        let finished_processes = FINISHED_PROCESSES.get_or_init(|| init_finished_processes());
+        let mut finished_processes = finished_processes.lock().unwrap();
+        finished_processes.retain(|finished_process| finished_process.task_id != task_id);
+        finished_processes.push_back(process_info);
+        while finished_processes.len() > common::FINISHED_PROCESS_HISTORY_SIZE {
+            if let Some(finished_process) = finished_processes.pop_front() {
+                model_log_service::remove_model_log(finished_process.task_id.as_str());
+            }
+        }
+    }
 }
 
 pub fn initialize_process_service() {
@@ -118,110 +390,384 @@ pub fn get_processes() -> Vec<ProcessInfo> {
     map.values().cloned().collect::<Vec<_>>()
 }
 
+/// Processes stopped or failed recently with their final state and reason, oldest first
+pub fn get_finished_processes() -> Vec<ProcessInfo> {
+    let finished_processes = FINISHED_PROCESSES.get_or_init(|| init_finished_processes());
+    finished_processes.lock().unwrap().iter().cloned().collect()
+}
+
+/// Whether process is registered and not asked to stop
 pub fn has_process(task_id: &str) -> bool {
     let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
-    let mut map = map_ref.lock().unwrap();
-    map.contains_key(task_id)
+    let map = map_ref.lock().unwrap();
+    map.get(task_id)
+        .map(|process_info| process_info.state != ProcessState::Stopping)
+        .unwrap_or(false)
 }
 
+/// Ask process to stop, its supervisor terminates it and removes it once it exits. Model server
+/// running in this process is cancelled and removed once its thread returns.
 pub fn stop_process(task_id: &str) {
-    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
-    let mut map = map_ref.lock().unwrap();
-    map.remove(task_id);
+    {
+        let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+        let mut map = map_ref.lock().unwrap();
+        if let Some(process_info) = map.get_mut(task_id) {
+            if process_info.state != ProcessState::Stopping {
+                transition_process(process_info, ProcessState::Stopping, None);
+            }
+        }
+    }
+    cancel_in_process_server(task_id);
 }
 
+/// Stop all model processes and wait until they exit, processes are terminated gracefully by
+/// their supervisors same as stop_process.
 pub fn stop_all_processes() {
+    let task_ids = {
+        let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+        let map = map_ref.lock().unwrap();
+        map.keys().cloned().collect::<Vec<_>>()
+    };
+    task_ids
+        .iter()
+        .for_each(|task_id| stop_process(task_id.as_str()));
+    let config = config::Config::new();
+    // Give supervisors extra time to force kill processes after grace period
+    let timeout = Duration::from_secs(config.get_config_model_stop_timeout() + 5);
+    let start_time = SystemTime::now();
+    while ALIVE_PROCESSES.load(Ordering::SeqCst) > 0
+        && start_time.elapsed().unwrap_or_default() < timeout
+    {
+        sleep(Duration::from_millis(100));
+    }
     let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
     let mut map = map_ref.lock().unwrap();
+    map.values().for_each(|process_info| release_process_port(process_info));
     map.clear();
-    sleep(Duration::from_millis(2000));
 }
 
-pub fn notify_process_running(task_id: &str) {
+fn release_process_port(process_info: &ProcessInfo) {
+    if let Ok(port) = process_info.port.parse::<u16>() {
+        synvek::release_port_number(port);
+    }
+}
+
+/// Update state reported by model process. State is ignored once process is asked to stop or
+/// failed, so late report from exited process doesn't revive it.
+pub fn update_process_state(task_id: &str, state: ProcessState, reason: Option<String>) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    let Some(process_info) = map.get_mut(task_id) else {
+        tracing::warn!("Process state {:?} is reported on unknown task {}", state, task_id);
+        return;
+    };
+    if matches!(
+        process_info.state,
+        ProcessState::Stopping | ProcessState::Stopped | ProcessState::Failed
+    ) || process_info.state == state
+    {
+        return;
+    }
+    if state == ProcessState::Ready {
+        // Idle time is counted from model server is ready
+        process_info.last_activity_time = get_now_time();
+    }
+    transition_process(process_info, state, reason);
+}
+
+/// Record a request seen on model process, it is used for idle timeout
+pub fn touch_process(task_id: &str) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    if let Some(process_info) = map.get_mut(task_id) {
+        process_info.last_activity_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+    }
+}
+
+/// Count request which starts on model process, it is used for least-busy routing
+pub fn begin_process_request(task_id: &str) {
     let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
     let mut map = map_ref.lock().unwrap();
-    // let mut process_info_option = map.get_mut(&task_id);
-    // if process_info_option.is_some() {
-    //     process_info.running = true;
-    // }
-    tracing::info!(
-        "Notify process running with task {} and data {:?}",
-        task_id,
-        map
-    );
     if let Some(process_info) = map.get_mut(task_id) {
-        process_info.running = true;
-        tracing::info!(
-            "Notify process updated with task {} and data {:?}",
+        process_info.active_requests += 1;
+    }
+}
+
+/// Count request which ends on model process
+pub fn end_process_request(task_id: &str) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    if let Some(process_info) = map.get_mut(task_id) {
+        // Count is reset when process restarts, so requests of previous process are ignored
+        process_info.active_requests = process_info.active_requests.saturating_sub(1);
+    }
+}
+
+/// Get running processes which have seen no request longer than their idle timeout. Processes with
+/// requests in flight or waiting in queue are never idle, since streamed responses may last longer
+/// than idle timeout.
+pub fn get_idle_processes() -> Vec<ProcessInfo> {
+    let idle_processes = {
+        let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+        let map = map_ref.lock().unwrap();
+        let now_time = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        map.values()
+            .filter(|process_info| {
+                if let Some(idle_timeout) = process_info.idle_timeout {
+                    process_info.is_ready()
+                        && process_info.active_requests == 0
+                        && now_time >= process_info.last_activity_time + idle_timeout
+                } else {
+                    false
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    idle_processes
+        .into_iter()
+        .filter(|process_info| concurrency_service::get_queued_requests(process_info.task_id.as_str()) == 0)
+        .collect()
+}
+
+fn get_process(task_id: &str) -> Option<ProcessInfo> {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let map = map_ref.lock().unwrap();
+    map.get(task_id).cloned()
+}
+
+/// Whether model server is running on thread of this process
+pub fn is_in_process_server(task_id: &str) -> bool {
+    get_process(task_id)
+        .map(|process_info| matches!(process_info.process_type, ProcessType::InProcess))
+        .unwrap_or(false)
+}
+
+fn update_process_restarting(task_id: &str, restart_count: u32, last_exit_status: String) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    if let Some(process_info) = map.get_mut(task_id) {
+        process_info.restart_count = restart_count;
+        process_info.last_exit_status = Some(last_exit_status.clone());
+        if process_info.state != ProcessState::Stopping {
+            transition_process(process_info, ProcessState::Failed, Some(last_exit_status));
+        }
+    }
+}
+
+fn update_process_id(task_id: &str, process_id: &str) {
+    let map_ref = Arc::clone(GLOBAL_PROCESSES.get().unwrap());
+    let mut map = map_ref.lock().unwrap();
+    if let Some(process_info) = map.get_mut(task_id) {
+        process_info.process_id = process_id.to_string();
+    }
+}
+
+/// Check if model process should be restarted with restart policy and failed retry count
+fn should_restart_process(
+    restart_policy: &str,
+    success: bool,
+    retry_count: u32,
+    max_retries: u32,
+) -> bool {
+    if restart_policy == common::RESTART_POLICY_ALWAYS {
+        true
+    } else if restart_policy == common::RESTART_POLICY_ON_FAILURE {
+        !success && retry_count < max_retries
+    } else {
+        false
+    }
+}
+
+/// Exponential backoff before restart, it is doubled on each retry until max backoff
+fn get_restart_backoff(retry_count: u32) -> Duration {
+    let backoff = common::RESTART_BACKOFF_BASE_SECS.saturating_mul(1u64 << retry_count.min(16));
+    Duration::from_secs(backoff.min(common::RESTART_BACKOFF_MAX_SECS))
+}
+
+/// Wait for restart backoff, return false if process is stopped in the meantime
+fn wait_for_restart(task_id: &str, backoff: Duration) -> bool {
+    let start_time = SystemTime::now();
+    while start_time.elapsed().unwrap_or_default() < backoff {
+        if !has_process(task_id) {
+            return false;
+        }
+        sleep(Duration::from_millis(500));
+    }
+    has_process(task_id)
+}
+
+/// Register model server which runs on thread of this process. Receiver is resolved once model
+/// server is asked to stop, none is returned if task is being stopped already.
+pub fn register_in_process_server(
+    task_id: &str,
+    port: &str,
+    model_args: &ModelServiceArgs,
+) -> Option<oneshot::Receiver<()>> {
+    let mut process_info =
+        create_process_info(task_id, ProcessType::InProcess, port, model_args);
+    process_info.process_id = std::process::id().to_string();
+    // Cancellation is registered first so stop request right after insert is not lost
+    let (sender, receiver) = oneshot::channel();
+    let cancellations = GLOBAL_CANCELLATIONS.get_or_init(|| init_cancellations());
+    cancellations
+        .lock()
+        .unwrap()
+        .insert(task_id.to_string(), sender);
+    if !insert_process(task_id.to_string(), process_info) {
+        cancellations.lock().unwrap().remove(task_id);
+        return None;
+    }
+    ALIVE_PROCESSES.fetch_add(1, Ordering::SeqCst);
+    Some(receiver)
+}
+
+/// Remove model server running in this process after its thread returns. It is stopped if it was
+/// asked to stop, otherwise it exited unexpectedly.
+pub fn finish_in_process_server(task_id: &str) {
+    let cancellations = GLOBAL_CANCELLATIONS.get_or_init(|| init_cancellations());
+    cancellations.lock().unwrap().remove(task_id);
+    let stopping = get_process(task_id)
+        .map(|process_info| process_info.state == ProcessState::Stopping)
+        .unwrap_or(true);
+    if stopping {
+        finish_process(task_id, ProcessState::Stopped, None);
+    } else {
+        finish_process(
             task_id,
-            map
+            ProcessState::Failed,
+            Some("Model server exited unexpectedly".to_string()),
+        );
+        system_service::send_message(
+            MessageSource::ProcessService,
+            MessageType::ProcessTerminatedUnexpected,
+            format!("Model server exited unexpectedly on task id: {}", task_id),
         );
     }
+    ALIVE_PROCESSES.fetch_sub(1, Ordering::SeqCst);
 }
 
 pub fn start_process(task_id: String, process_args: Vec<String>, model_args: ModelServiceArgs) {
     let current_exe = env::current_exe().unwrap();
     let current_dir = env::current_dir().unwrap();
     let _ = thread::spawn(move || {
-        let mut child = Command::new(current_exe)
-            //.creation_flags(CREATE_NO_WINDOW )
-            .current_dir(current_dir)
-            .args(process_args.clone())
-            // .stdout(Stdio::null()) // 可选：丢弃标准输出，避免任何可能的输出窗口
-            // .stderr(Stdio::null()) // 可选：丢弃标准错误输出
-            // .stdin(Stdio::null())
-            // .stdin(Stdio::piped())   // 根据需要配置输入输出
-            // .stdout(Stdio::piped())
-            // .stderr(Stdio::piped())
-            .spawn()
-            // .unwrap().wait()
-            .expect("Failed to spawn child");
-        // let stdout = child.stdout.take().ok_or("无法获取标准输出".to_string());
-        // let stdin = child.stdin.take().ok_or("无法获取标准输出".to_string());
-        // let stderr = child.stderr.take().ok_or("无法获取标准输出".to_string());
-
-        let process_id = child.id().to_string();
-        let process_info = ProcessInfo {
-            task_id: task_id.clone(),
-            process_id: child.id().to_string(),
-            process_type: ProcessType::ModelServer,
-            create_time: SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            running: false,
-            model_name: model_args.model_name.clone(),
-            model_id: model_args.model_id.clone(),
-            model_type: model_args.model_type.clone(),
-            port: model_args.port.clone(),
-            token_source: model_args.token_source.clone(),
-            isq: model_args.isq.clone(),
-            path: model_args.path.clone(),
-            cpu: model_args.cpu,
-            offloaded: model_args.offloaded,
-            backend: model_args.backend,
-            acceleration: model_args.acceleration,
-            context_length: model_args.context_length,
-            gpu_layers: model_args.gpu_layers,
-            cpu_threads: model_args.cpu_threads,
-            batch_size: model_args.batch_size,
-            rope_scaling: model_args.rope_scaling,
-            rope_scale: model_args.rope_scale,
-            rope_freq_base: model_args.rope_freq_base,
-        };
-        insert_process(task_id.clone(), process_info);
-
-        let mut heart_tick =  SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let restart_policy = model_args
+            .restart_policy
+            .clone()
+            .unwrap_or(common::RESTART_POLICY_NEVER.to_string());
+        let restart_max_retries = model_args
+            .restart_max_retries
+            .unwrap_or(common::RESTART_MAX_RETRIES);
+        let mut restart_count: u32 = 0;
+        let mut retry_count: u32 = 0;
+        let mut last_exit_status: Option<String> = None;
+        let resource_limits = resource_service::prepare_resource_limits(task_id.as_str(), &model_args);
         loop {
-            match child.try_wait() {
-                Ok(Some(status)) => {
-                    tracing::warn!(
-                        "Model service process exited unexpectedly on task id: {} and process id: {} with {}",
+            let mut process_info = create_process_info(
+                task_id.as_str(),
+                ProcessType::ModelServer,
+                model_args.port.as_str(),
+                &model_args,
+            );
+            process_info.restart_count = restart_count;
+            process_info.last_exit_status = last_exit_status.clone();
+            if !insert_process(task_id.clone(), process_info) {
+                tracing::info!(
+                    "Model service process restart is cancelled on task id: {}",
+                    task_id
+                );
+                finish_process(task_id.as_str(), ProcessState::Stopped, None);
+                break;
+            }
+
+            let mut command = Command::new(current_exe.clone());
+            command
+                //.creation_flags(CREATE_NO_WINDOW )
+                .current_dir(current_dir.clone())
+                .args(process_args.clone())
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped());
+            resource_service::apply_resource_limits(&mut command, &resource_limits);
+            let child = command.spawn();
+            let mut child = match child {
+                Ok(mut child) => {
+                    model_log_service::capture_process_output(task_id.as_str(), &mut child);
+                    child
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to spawn model service process on task id: {} with error: {}",
                         task_id,
-                        process_id,
-                        status
+                        e
+                    );
+                    finish_process(
+                        task_id.as_str(),
+                        ProcessState::Failed,
+                        Some(format!("Failed to spawn process: {}", e)),
+                    );
+                    system_service::send_message(
+                        MessageSource::ProcessService,
+                        MessageType::ProcessFailedToStart,
+                        format!(
+                            "Failed to spawn model service process on task id: {} with error: {}",
+                            task_id,
+                            e
+                        ),
+                    );
+                    break;
+                }
+            };
+
+            let process_id = child.id().to_string();
+            update_process_id(task_id.as_str(), process_id.as_str());
+
+            ALIVE_PROCESSES.fetch_add(1, Ordering::SeqCst);
+            let status = supervise_process(task_id.as_str(), process_id.as_str(), &mut child);
+            ALIVE_PROCESSES.fetch_sub(1, Ordering::SeqCst);
+            let Some(status) = status else {
+                break;
+            };
+            // Failed retries are counted again once model server had been up and running
+            if let Some(process_info) = get_process(task_id.as_str()) {
+                if matches!(
+                    process_info.state,
+                    ProcessState::Ready | ProcessState::Unhealthy
+                ) {
+                    retry_count = 0;
+                }
+            }
+            if !should_restart_process(
+                restart_policy.as_str(),
+                status.success(),
+                retry_count,
+                restart_max_retries,
+            ) {
+                let state = if status.success() {
+                    ProcessState::Stopped
+                } else {
+                    ProcessState::Failed
+                };
+                finish_process(task_id.as_str(), state, Some(status.to_string()));
+                // Model server exited for good is not restored on next service start, otherwise
+                // autostart model which keeps crashing is launched again on every start
+                model_service::delete_model_servers_config(task_id.as_str());
+                if status.success() {
+                    system_service::send_message(
+                        MessageSource::ProcessService,
+                        MessageType::ProcessTerminatedNormally,
+                        format!(
+                            "Model service process exited normally on task id: {} and process id: {}",
+                            task_id, process_id
+                        ),
                     );
-                    stop_process(task_id.as_str());
+                } else {
                     system_service::send_message(
                         MessageSource::ProcessService,
                         MessageType::ProcessTerminatedUnexpected,
@@ -232,95 +778,200 @@ pub fn start_process(task_id: String, process_args: Vec<String>, model_args: Mod
                             status
                         ),
                     );
-                    break;
-                }
-                Ok(None) => {
-                    let running = has_process(task_id.as_str());
-                    if running {
-                        let mut now_time =  SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
-                        if now_time >= heart_tick + 60 {
-                            heart_tick = now_time;
-                            tracing::info!(
-                                "Model service process keep running on task id: {} and process id: {}",
-                                task_id,
-                                process_id
-                            );
-
-                            system_service::send_message(
-                                MessageSource::ProcessService,
-                                MessageType::ProcessRunning,
-                                format!(
-                                    "Model service process keep running on task id: {} and process id: {}",
-                                    task_id,
-                                    process_id
-                                ),
-                            );
-                            //thread::sleep(Duration::from_millis(1));
-                        }
-                    } else {
-                        tracing::info!(
-                            "Process will exit by signal on task id: {} and process id: {}",
-                            task_id,
-                            process_id
-                        );
-                        let kill_result = child.kill();
-                        if kill_result.is_ok() {
-                            tracing::info!(
-                                "Process is killed on task id: {} and process id: {}",
-                                task_id,
-                                process_id
-                            );
-                            system_service::send_message(
-                                MessageSource::ProcessService,
-                                MessageType::ProcessTerminatedNormally,
-                                format!(
-                                    "Process is killed on task id: {} and process id: {}",
-                                    task_id,
-                                    process_id
-                                ),
-                            );
-                        } else {
-                            tracing::error!(
-                                "Process failed to be killed on task id: {} and process id: {}",
-                                task_id,
-                                process_id
-                            );
-                            system_service::send_message(
-                                MessageSource::ProcessService,
-                                MessageType::ProcessFailedToStart,
-                                format!(
-                                    "Process failed to be killed on task id: {} and process id: {}",
-                                    task_id,
-                                    process_id
-                                ),
-                            );
-                        }
-                        break;
-                    }
                 }
-                Err(e) => {
-                    tracing::error!(
-                        "Error on start model service on task id: {} and process id: {} with error: {}",
+                break;
+            }
+            let backoff = get_restart_backoff(retry_count);
+            retry_count += 1;
+            restart_count += 1;
+            last_exit_status = Some(status.to_string());
+            update_process_restarting(task_id.as_str(), restart_count, status.to_string());
+            tracing::info!(
+                "Model service process will restart in {} seconds on task id: {} with restart count: {}",
+                backoff.as_secs(),
+                task_id,
+                restart_count
+            );
+            system_service::send_message(
+                MessageSource::ProcessService,
+                MessageType::ProcessRestarting,
+                format!(
+                    "Model service process will restart in {} seconds on task id: {} with restart count: {}",
+                    backoff.as_secs(),
+                    task_id,
+                    restart_count
+                ),
+            );
+            if !wait_for_restart(task_id.as_str(), backoff) {
+                tracing::info!(
+                    "Model service process restart is cancelled on task id: {}",
+                    task_id
+                );
+                finish_process(task_id.as_str(), ProcessState::Stopped, None);
+                break;
+            }
+        }
+        resource_service::release_resource_limits(task_id.as_str());
+    });
+}
+
+/// Ask process to exit by SIGTERM so backend can release resources, and kill it if it doesn't exit
+/// in grace period. Process is killed directly on platforms without SIGTERM.
+fn terminate_process(task_id: &str, process_id: &str, child: &mut Child) {
+    let config = config::Config::new();
+    let grace_period = Duration::from_secs(config.get_config_model_stop_timeout());
+    if send_terminate_signal(child) {
+        let start_time = SystemTime::now();
+        while start_time.elapsed().unwrap_or_default() < grace_period {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    tracing::info!(
+                        "Process is stopped gracefully on task id: {} and process id: {} with {}",
                         task_id,
                         process_id,
-                        e
+                        status
                     );
-                    stop_process(task_id.as_str());
                     system_service::send_message(
                         MessageSource::ProcessService,
-                        MessageType::ProcessFailedToStart,
+                        MessageType::ProcessStoppedGracefully,
                         format!(
-                            "Error on start model service on task id: {} and process id: {} with error: {}",
-                            task_id,
-                            process_id,
-                            e
+                            "Process is stopped gracefully on task id: {} and process id: {}",
+                            task_id, process_id
                         ),
                     );
+                    return;
+                }
+                Ok(None) => sleep(Duration::from_millis(100)),
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to wait process on task id: {} and process id: {} with error: {}",
+                        task_id,
+                        process_id,
+                        e
+                    );
                     break;
                 }
             }
         }
-    });
+    }
+    let kill_result = child.kill();
+    if kill_result.is_ok() {
+        let _ = child.wait();
+        tracing::warn!(
+            "Process is killed after timeout on task id: {} and process id: {}",
+            task_id,
+            process_id
+        );
+        system_service::send_message(
+            MessageSource::ProcessService,
+            MessageType::ProcessKilledAfterTimeout,
+            format!(
+                "Process is killed after timeout on task id: {} and process id: {}",
+                task_id, process_id
+            ),
+        );
+    } else {
+        tracing::error!(
+            "Process failed to be killed on task id: {} and process id: {}",
+            task_id,
+            process_id
+        );
+        system_service::send_message(
+            MessageSource::ProcessService,
+            MessageType::ProcessFailedToTerminate,
+            format!(
+                "Process failed to be killed on task id: {} and process id: {}",
+                task_id, process_id
+            ),
+        );
+    }
+}
+
+#[cfg(unix)]
+fn send_terminate_signal(child: &Child) -> bool {
+    let result = unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    result == 0
+}
+
+#[cfg(not(unix))]
+fn send_terminate_signal(_child: &Child) -> bool {
+    false
+}
+
+/// Supervise model process until it exits. Exit status is returned if process exited by itself,
+/// or None if process is stopped by signal or failed.
+fn supervise_process(task_id: &str, process_id: &str, child: &mut Child) -> Option<ExitStatus> {
+    let mut heart_tick = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                tracing::warn!(
+                    "Model service process exited unexpectedly on task id: {} and process id: {} with {}",
+                    task_id,
+                    process_id,
+                    status
+                );
+                return Some(status);
+            }
+            Ok(None) => {
+                let running = has_process(task_id);
+                if running {
+                    let now_time = SystemTime::now()
+                        .duration_since(SystemTime::UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs();
+                    if now_time >= heart_tick + 60 {
+                        heart_tick = now_time;
+                        tracing::info!(
+                            "Model service process keep running on task id: {} and process id: {}",
+                            task_id,
+                            process_id
+                        );
+
+                        system_service::send_message(
+                            MessageSource::ProcessService,
+                            MessageType::ProcessRunning,
+                            format!(
+                                "Model service process keep running on task id: {} and process id: {}",
+                                task_id, process_id
+                            ),
+                        );
+                        //thread::sleep(Duration::from_millis(1));
+                    }
+                } else {
+                    tracing::info!(
+                        "Process will exit by signal on task id: {} and process id: {}",
+                        task_id,
+                        process_id
+                    );
+                    terminate_process(task_id, process_id, child);
+                    finish_process(task_id, ProcessState::Stopped, None);
+                    return None;
+                }
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Error on start model service on task id: {} and process id: {} with error: {}",
+                    task_id,
+                    process_id,
+                    e
+                );
+                finish_process(task_id, ProcessState::Failed, Some(e.to_string()));
+                system_service::send_message(
+                    MessageSource::ProcessService,
+                    MessageType::ProcessFailedToStart,
+                    format!(
+                        "Error on start model service on task id: {} and process id: {} with error: {}",
+                        task_id, process_id, e
+                    ),
+                );
+                return None;
+            }
+        }
+    }
 }
 
 pub fn notify_main_process_sync(task_id: &str) {
@@ -346,11 +997,22 @@ pub async fn notify_main_process_dummy(_: String) -> anyhow::Result<()> {
 }
 
 pub async fn notify_main_process(task_id: &str) -> anyhow::Result<()> {
+    notify_main_process_state(task_id, ProcessState::Ready, None).await
+}
+
+/// Report state of model server to main process
+pub async fn notify_main_process_state(
+    task_id: &str,
+    state: ProcessState,
+    reason: Option<String>,
+) -> anyhow::Result<()> {
     let client = Client::builder().timeout(Duration::from_secs(5)).build()?;
     let train_config = config::get_synvek_config();
     let port = train_config.port.to_string();
     let request_data = HeartTickRequest {
         task_id: task_id.to_string(),
+        state: Some(state),
+        reason,
     };
 
     let mut main_process_address = "http://127.0.0.1:".to_string();
@@ -0,0 +1,241 @@
+use crate::common;
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Mutex;
+
+/// Named launch settings of a model, fields which are not set are taken from start request
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LaunchProfile {
+    /// Profile Name, e.g. long-context or cpu-only
+    pub profile_name: String,
+
+    /// Model ID
+    #[serde(default)]
+    pub model_id: Option<String>,
+
+    /// Model Type
+    #[serde(default)]
+    pub model_type: Option<String>,
+
+    /// ISQ
+    #[serde(default)]
+    pub isq: Option<String>,
+
+    /// Use CPU
+    #[serde(default)]
+    pub cpu: Option<bool>,
+
+    /// Offloaded
+    #[serde(default)]
+    pub offloaded: Option<bool>,
+
+    /// Backend
+    #[serde(default)]
+    pub backend: Option<String>,
+
+    /// Acceleration
+    #[serde(default)]
+    pub acceleration: Option<String>,
+
+    /// Context Length
+    #[serde(default)]
+    pub context_length: Option<u32>,
+
+    /// GPU layers (offload)
+    #[serde(default)]
+    pub gpu_layers: Option<u32>,
+
+    /// CPU threads
+    #[serde(default)]
+    pub cpu_threads: Option<u32>,
+
+    /// Batch size
+    #[serde(default)]
+    pub batch_size: Option<u32>,
+
+    /// RoPE scaling method
+    #[serde(default)]
+    pub rope_scaling: Option<String>,
+
+    /// RoPE scale
+    #[serde(default)]
+    pub rope_scale: Option<u32>,
+
+    /// RoPE frequency base
+    #[serde(default)]
+    pub rope_freq_base: Option<u32>,
+}
+
+/// Launch profiles of a task
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TaskProfiles {
+    pub task_name: String,
+
+    /// Profile used when start request doesn't name one
+    #[serde(default)]
+    pub default_profile: Option<String>,
+
+    #[serde(default)]
+    pub profiles: Vec<LaunchProfile>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ProfileConfigs {
+    pub task_profiles: Vec<TaskProfiles>,
+}
+
+static PROFILES_CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+fn load_profiles_config() -> ProfileConfigs {
+    let config = Config::new();
+    let mut profiles_config = config.get_config_dir();
+    profiles_config.push(common::PROFILES_FILE);
+    let data_result = fs::read_to_string(profiles_config.clone());
+    if let Ok(data) = data_result {
+        let profiles_result = serde_json::from_str(&data);
+        if let Ok(profiles) = profiles_result {
+            return profiles;
+        } else {
+            tracing::error!(
+                "Invalid profiles config file: {}",
+                profiles_config.display()
+            );
+        }
+    }
+    ProfileConfigs::default()
+}
+
+fn write_profiles_config(profiles: &ProfileConfigs) -> anyhow::Result<()> {
+    let config = Config::new();
+    let mut profiles_config = config.get_config_dir();
+    profiles_config.push(common::PROFILES_FILE);
+    let json = serde_json::to_string_pretty(profiles)?;
+    fs::write(profiles_config, json)?;
+    Ok(())
+}
+
+/// Update profiles of task and save them, task profiles are removed once they are empty
+fn update_task_profiles<F>(task_name: &str, update: F) -> anyhow::Result<TaskProfiles>
+where
+    F: FnOnce(&mut TaskProfiles) -> anyhow::Result<()>,
+{
+    let _lock = PROFILES_CONFIG_LOCK.lock().unwrap();
+    let mut profiles_config = load_profiles_config();
+    let index = profiles_config
+        .task_profiles
+        .iter()
+        .position(|element| element.task_name == task_name);
+    let mut task_profiles = match index {
+        Some(index) => profiles_config.task_profiles.remove(index),
+        None => TaskProfiles {
+            task_name: task_name.to_string(),
+            ..Default::default()
+        },
+    };
+    update(&mut task_profiles)?;
+    if !task_profiles.profiles.is_empty() {
+        profiles_config.task_profiles.push(task_profiles.clone());
+    }
+    write_profiles_config(&profiles_config)?;
+    Ok(task_profiles)
+}
+
+pub fn get_task_profiles(task_name: &str) -> TaskProfiles {
+    let _lock = PROFILES_CONFIG_LOCK.lock().unwrap();
+    load_profiles_config()
+        .task_profiles
+        .into_iter()
+        .find(|element| element.task_name == task_name)
+        .unwrap_or(TaskProfiles {
+            task_name: task_name.to_string(),
+            ..Default::default()
+        })
+}
+
+/// Create or replace profile of task, the first profile of task becomes default profile
+pub fn save_profile(
+    task_name: &str,
+    profile: &LaunchProfile,
+    set_default: bool,
+) -> anyhow::Result<TaskProfiles> {
+    if profile.profile_name.trim().is_empty() {
+        return Err(anyhow::anyhow!("Profile name is required"));
+    }
+    update_task_profiles(task_name, |task_profiles| {
+        let index = task_profiles
+            .profiles
+            .iter()
+            .position(|element| element.profile_name == profile.profile_name);
+        if let Some(index) = index {
+            task_profiles.profiles[index] = profile.clone();
+        } else {
+            task_profiles.profiles.push(profile.clone());
+        }
+        if set_default || task_profiles.default_profile.is_none() {
+            task_profiles.default_profile = Some(profile.profile_name.clone());
+        }
+        Ok(())
+    })
+}
+
+pub fn delete_profile(task_name: &str, profile_name: &str) -> anyhow::Result<TaskProfiles> {
+    update_task_profiles(task_name, |task_profiles| {
+        let count = task_profiles.profiles.len();
+        task_profiles
+            .profiles
+            .retain(|element| element.profile_name != profile_name);
+        if task_profiles.profiles.len() == count {
+            return Err(anyhow::anyhow!("Profile not found: {}", profile_name));
+        }
+        if task_profiles.default_profile.as_deref() == Some(profile_name) {
+            task_profiles.default_profile = None;
+        }
+        Ok(())
+    })
+}
+
+/// Set default profile of task, default profile is cleared if profile name is not provided
+pub fn set_default_profile(
+    task_name: &str,
+    profile_name: Option<String>,
+) -> anyhow::Result<TaskProfiles> {
+    update_task_profiles(task_name, |task_profiles| {
+        if let Some(profile_name) = profile_name.as_ref() {
+            if !task_profiles
+                .profiles
+                .iter()
+                .any(|element| &element.profile_name == profile_name)
+            {
+                return Err(anyhow::anyhow!("Profile not found: {}", profile_name));
+            }
+        }
+        task_profiles.default_profile = profile_name;
+        Ok(())
+    })
+}
+
+/// Find profile to launch task with. Named profile must exist, and default profile of task is used
+/// if no profile is named.
+pub fn resolve_profile(
+    task_name: &str,
+    profile_name: Option<&str>,
+) -> anyhow::Result<Option<LaunchProfile>> {
+    let task_profiles = get_task_profiles(task_name);
+    let Some(profile_name) = profile_name.or(task_profiles.default_profile.as_deref()) else {
+        return Ok(None);
+    };
+    let profile = task_profiles
+        .profiles
+        .iter()
+        .find(|element| element.profile_name == profile_name)
+        .cloned();
+    if profile.is_none() {
+        return Err(anyhow::anyhow!(
+            "Profile {} not found on model {}",
+            profile_name,
+            task_name
+        ));
+    }
+    Ok(profile)
+}
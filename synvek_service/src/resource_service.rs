@@ -0,0 +1,243 @@
+use crate::model_service::ModelServiceArgs;
+use std::ffi::CString;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// Leaf cgroup of service, model cgroups are created next to it
+const SERVICE_CGROUP_NAME: &str = "synvek-service";
+
+/// Cgroup whose children are service cgroup and model cgroups, error is kept if it can't be prepared
+static CGROUP_ROOT: OnceLock<Result<PathBuf, String>> = OnceLock::new();
+
+/// Resource limits of model process, they are prepared in main process and applied in model process
+/// before exec so all threads of backend inherit them.
+#[derive(Debug, Clone, Default)]
+pub struct ResourceLimits {
+    /// Maximum memory in bytes
+    pub memory_limit: Option<u64>,
+
+    /// cgroup.procs of cgroup prepared for model process, process fails to spawn if it is none while
+    /// memory limit is set
+    pub cgroup_procs: Option<CString>,
+
+    pub cpu_affinity: Option<Vec<usize>>,
+
+    pub nice: Option<i32>,
+}
+
+/// Check memory limit can be applied before model server is started. Memory limit is not supported
+/// without cgroup v2, since address space limit breaks CUDA and mmap backends which reserve much
+/// more than they use.
+pub fn check_memory_limit() -> anyhow::Result<()> {
+    get_cgroup_root().map(|_| ())
+}
+
+/// Prepare resource limits of model server, cgroup v2 is created for memory limit. Model process
+/// fails to spawn if memory limit is requested and cgroup can't be prepared.
+pub fn prepare_resource_limits(task_id: &str, args: &ModelServiceArgs) -> ResourceLimits {
+    let memory_limit = args
+        .memory_limit
+        .map(|memory_limit| memory_limit.saturating_mul(1024 * 1024));
+    let mut cgroup_procs: Option<CString> = None;
+    if let Some(memory_limit) = memory_limit {
+        match prepare_cgroup(task_id, memory_limit) {
+            Ok(procs) => {
+                tracing::info!(
+                    "Memory limit {} is applied by cgroup on task id: {}",
+                    memory_limit,
+                    task_id
+                );
+                cgroup_procs = Some(procs);
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Cgroup is not available on task id: {} with reason: {}, memory limit can't be applied",
+                    task_id,
+                    e
+                );
+            }
+        }
+    }
+    ResourceLimits {
+        memory_limit,
+        cgroup_procs,
+        cpu_affinity: args.cpu_affinity.clone(),
+        nice: args.nice,
+    }
+}
+
+/// Remove cgroup created for model process, it only succeeds after process exits.
+pub fn release_resource_limits(task_id: &str) {
+    if let Some(cgroup_dir) = get_model_cgroup_dir(task_id) {
+        if cgroup_dir.exists() {
+            let _ = fs::remove_dir(cgroup_dir);
+        }
+    }
+}
+
+/// Apply resource limits in forked process before exec, process fails to spawn if any of them
+/// can't be applied.
+#[cfg(unix)]
+pub fn apply_resource_limits(command: &mut Command, limits: &ResourceLimits) {
+    use std::os::unix::process::CommandExt;
+    let limits = limits.clone();
+    // Only async signal safe calls are allowed between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            if let Some(cgroup_procs) = limits.cgroup_procs.as_ref() {
+                join_cgroup(cgroup_procs)?;
+            } else if limits.memory_limit.is_some() {
+                return Err(std::io::Error::from_raw_os_error(libc::ENOTSUP));
+            }
+            if let Some(cpu_affinity) = limits.cpu_affinity.as_ref() {
+                apply_cpu_affinity(cpu_affinity)?;
+            }
+            if let Some(nice) = limits.nice {
+                apply_nice(nice)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub fn apply_resource_limits(_command: &mut Command, limits: &ResourceLimits) {
+    if limits.memory_limit.is_some() || limits.cpu_affinity.is_some() || limits.nice.is_some() {
+        tracing::warn!("Resource limits are not supported on this platform");
+    }
+}
+
+fn get_cgroup_root() -> anyhow::Result<PathBuf> {
+    CGROUP_ROOT
+        .get_or_init(|| prepare_cgroup_root().map_err(|e| e.to_string()))
+        .clone()
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Prepare cgroup v2 for model cgroups. Cgroup which enables controllers for its children can't
+/// hold processes itself, so service moves into leaf cgroup first and then memory controller is
+/// enabled on cgroup it came from.
+#[cfg(target_os = "linux")]
+fn prepare_cgroup_root() -> anyhow::Result<PathBuf> {
+    let cgroup_root = PathBuf::from("/sys/fs/cgroup");
+    if !cgroup_root.join("cgroup.controllers").exists() {
+        return Err(anyhow::anyhow!("Cgroup v2 is not available"));
+    }
+    let cgroup = fs::read_to_string("/proc/self/cgroup")?;
+    let cgroup_path = cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or(anyhow::anyhow!("Cgroup v2 of service is not found"))?
+        .trim_start_matches('/');
+    let mut cgroup_dir = cgroup_root;
+    if !cgroup_path.is_empty() {
+        cgroup_dir.push(cgroup_path);
+    }
+    if cgroup_dir.file_name().is_some_and(|name| name == SERVICE_CGROUP_NAME) {
+        cgroup_dir.pop();
+    } else {
+        let service_cgroup_dir = cgroup_dir.join(SERVICE_CGROUP_NAME);
+        if !service_cgroup_dir.exists() {
+            fs::create_dir(service_cgroup_dir.clone())?;
+        }
+        fs::write(
+            service_cgroup_dir.join("cgroup.procs"),
+            std::process::id().to_string(),
+        )?;
+        tracing::info!(
+            "Service is moved into cgroup: {}",
+            service_cgroup_dir.display()
+        );
+    }
+    let subtree_control_path = cgroup_dir.join("cgroup.subtree_control");
+    let subtree_control = fs::read_to_string(subtree_control_path.clone())?;
+    if !subtree_control
+        .split_whitespace()
+        .any(|controller| controller == "memory")
+    {
+        fs::write(subtree_control_path, "+memory").map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to enable memory controller on cgroup: {} with error: {}",
+                cgroup_dir.display(),
+                e
+            )
+        })?;
+    }
+    Ok(cgroup_dir)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn prepare_cgroup_root() -> anyhow::Result<PathBuf> {
+    Err(anyhow::anyhow!("Cgroup v2 is not available"))
+}
+
+/// Cgroup of model process, it is sibling of service cgroup. None is returned if cgroup root is
+/// not prepared.
+fn get_model_cgroup_dir(task_id: &str) -> Option<PathBuf> {
+    let cgroup_root = CGROUP_ROOT.get()?.as_ref().ok()?;
+    Some(cgroup_root.join(format!("synvek-{}", task_id)))
+}
+
+fn prepare_cgroup(task_id: &str, memory_limit: u64) -> anyhow::Result<CString> {
+    let cgroup_root = get_cgroup_root()?;
+    let cgroup_dir = cgroup_root.join(format!("synvek-{}", task_id));
+    if !cgroup_dir.exists() {
+        fs::create_dir(cgroup_dir.clone())?;
+    }
+    fs::write(cgroup_dir.join("memory.max"), memory_limit.to_string())?;
+    // Swap is limited too, otherwise process keeps running on swap after memory limit is reached
+    let _ = fs::write(cgroup_dir.join("memory.swap.max"), "0");
+    let cgroup_procs = cgroup_dir.join("cgroup.procs");
+    Ok(CString::new(cgroup_procs.to_string_lossy().as_bytes())?)
+}
+
+/// Move current process into cgroup, 0 stands for the writing process
+#[cfg(unix)]
+fn join_cgroup(cgroup_procs: &CString) -> std::io::Result<()> {
+    unsafe {
+        let fd = libc::open(cgroup_procs.as_ptr(), libc::O_WRONLY);
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let result = libc::write(fd, b"0".as_ptr() as *const libc::c_void, 1);
+        libc::close(fd);
+        if result < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn apply_cpu_affinity(cpu_affinity: &[usize]) -> std::io::Result<()> {
+    let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+    for cpu in cpu_affinity {
+        if *cpu >= libc::CPU_SETSIZE as usize {
+            return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        unsafe { libc::CPU_SET(*cpu, &mut cpu_set) };
+    }
+    let result =
+        unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &cpu_set) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// CPU affinity is not supported by macOS, it is ignored there
+#[cfg(all(unix, not(target_os = "linux")))]
+fn apply_cpu_affinity(_cpu_affinity: &[usize]) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn apply_nice(nice: i32) -> std::io::Result<()> {
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
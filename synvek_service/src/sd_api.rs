@@ -1,7 +1,9 @@
 use actix_web::{web, App, HttpResponse, HttpServer, Responder};
 use actix_web::{get, post, HttpRequest};
+use actix_web::http::StatusCode;
 use serde::{Deserialize, Serialize};
-use crate::{process_service, sd_service};
+use crate::{common, concurrency_service, process_service, sd_service};
+use crate::concurrency_service::{ConcurrencyError, ConcurrencyLimit, ConcurrencyPermit};
 use crate::sd_service::{GenerationArgs, RefImage};
 
 /// Request for Generate Image
@@ -117,8 +119,52 @@ pub struct ImageEditResponse {
 
 }
 
+/// Wait for free slot of sd server since it generates one image at a time per slot. Error response
+/// is returned if queue is full or request times out in queue.
+async fn acquire_sd_server() -> Result<Option<ConcurrencyPermit>, HttpResponse> {
+    let sd_config = sd_service::get_sd_config();
+    let Some(limit) = ConcurrencyLimit::from_args(&sd_config.args) else {
+        return Ok(None);
+    };
+    let limiter = concurrency_service::get_limiter(sd_config.task_id.as_str(), limit);
+    match limiter.acquire().await {
+        Ok(permit) => Ok(Some(permit)),
+        Err(e) => {
+            let status = match e {
+                ConcurrencyError::QueueFull(_) => StatusCode::TOO_MANY_REQUESTS,
+                ConcurrencyError::Timeout(_) => StatusCode::SERVICE_UNAVAILABLE,
+            };
+            let response = ImageGenerationResponse {
+                success: false,
+                code: "".to_string(),
+                message: e.to_string(),
+                data: vec![],
+            };
+            Err(HttpResponse::build(status).json(response))
+        }
+    }
+}
+
+/// Generate images out of async worker so queued requests of this worker keep waiting
+async fn generate_image_blocking(generation_args: GenerationArgs) -> Vec<String> {
+    web::block(move || sd_service::generate_image(&generation_args))
+        .await
+        .unwrap_or_else(|e| {
+            tracing::error!("Failed to generate image with error: {}", e);
+            vec![]
+        })
+}
+
 #[post("/images/generations")]
 async fn generate(req: web::Json<ImageGenerationRequest>) -> impl Responder {
+    let concurrency_permit = match acquire_sd_server().await {
+        Ok(concurrency_permit) => concurrency_permit,
+        Err(response) => return response,
+    };
+    let queue_position = concurrency_permit
+        .as_ref()
+        .map(|concurrency_permit| concurrency_permit.queue_position)
+        .unwrap_or(0);
     let generation_args = GenerationArgs {
         model: req.model.clone(),
         prompt: req.prompt.clone(),
@@ -153,7 +199,8 @@ async fn generate(req: web::Json<ImageGenerationRequest>) -> impl Responder {
         control_strength: req.control_strength,
         control_net: req.control_net.clone(),
     };
-    let image_output = sd_service::generate_image(&generation_args);
+    let image_output = generate_image_blocking(generation_args).await;
+    drop(concurrency_permit);
     let mut image_data: Vec<ImageData> = vec![];
     image_output.iter().for_each(|output| {
        let image_item = ImageData {
@@ -168,11 +215,21 @@ async fn generate(req: web::Json<ImageGenerationRequest>) -> impl Responder {
         message: "".to_string(),
         data: image_data,
     };
-    HttpResponse::Ok().json(response)
+    HttpResponse::Ok()
+        .insert_header((common::QUEUE_POSITION_HEADER, queue_position.to_string()))
+        .json(response)
 }
 
 #[post("/images/edit")]
 async fn edit_image(req: web::Json<ImageEditRequest>) -> impl Responder {
+    let concurrency_permit = match acquire_sd_server().await {
+        Ok(concurrency_permit) => concurrency_permit,
+        Err(response) => return response,
+    };
+    let queue_position = concurrency_permit
+        .as_ref()
+        .map(|concurrency_permit| concurrency_permit.queue_position)
+        .unwrap_or(0);
     let image_edit_args = GenerationArgs {
         model: req.model.clone(),
         prompt: req.prompt.clone(),
@@ -207,7 +264,8 @@ async fn edit_image(req: web::Json<ImageEditRequest>) -> impl Responder {
         control_strength: req.control_strength,
         control_net: req.control_net.clone(),
     };
-    let image_output = sd_service::generate_image(&image_edit_args);
+    let image_output = generate_image_blocking(image_edit_args).await;
+    drop(concurrency_permit);
     let mut image_data: Vec<ImageData> = vec![];
     image_output.iter().for_each(|output| {
         let image_item = ImageData {
@@ -222,5 +280,7 @@ async fn edit_image(req: web::Json<ImageEditRequest>) -> impl Responder {
         message: "".to_string(),
         data: image_data,
     };
-    HttpResponse::Ok().json(response)
+    HttpResponse::Ok()
+        .insert_header((common::QUEUE_POSITION_HEADER, queue_position.to_string()))
+        .json(response)
 }
@@ -1,9 +1,10 @@
 use chrono::Local;
 use clap::Parser;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsString;
 use std::fmt::Debug;
 use std::fs::File;
+use std::net::TcpListener;
 use std::sync::atomic::AtomicBool;
 use std::sync::{Arc, Mutex, OnceLock};
 use std::{env, thread};
@@ -41,6 +42,8 @@ static LOGGER: std::sync::OnceLock<()> = std::sync::OnceLock::new();
 pub struct Settings {
     pub port: u16,
     pub port_count: u16,
+    /// Ports held by model servers, they are given back when model server stops
+    pub allocated_ports: HashSet<u16>,
 }
 
 static GLOBAL_SETTINGS: OnceLock<Arc<Mutex<Settings>>> = OnceLock::new();
@@ -51,6 +54,7 @@ fn init_settings() -> Arc<Mutex<Settings>> {
     Arc::new(Mutex::new(Settings {
         port: model_port,
         port_count: 0,
+        allocated_ports: HashSet::new(),
     }))
 }
 
@@ -59,15 +63,44 @@ fn update_settings(settings: Settings) {
     let mut global_settings = setting_ref.lock().unwrap();
     global_settings.port = settings.port;
     global_settings.port_count = settings.port_count;
+    global_settings.allocated_ports = settings.allocated_ports;
 }
 
-pub fn inc_port_number() -> u16 {
+/// Allocate a free port for model server within configured range. Port is checked by binding on it
+/// so ports used by other software are skipped.
+pub fn allocate_port_number() -> anyhow::Result<u16> {
+    let config = config::Config::new();
+    let host = config.get_config_host();
+    let port_range = config.get_config_model_port_range();
     let setting_ref = Arc::clone(GLOBAL_SETTINGS.get().unwrap());
     let mut global_settings = setting_ref.lock().unwrap();
-    let new_port_number = global_settings.port;
-    global_settings.port = global_settings.port + 1;
-    global_settings.port_count = global_settings.port_count + 1;
-    new_port_number
+    let first_port = global_settings.port;
+    let last_port = first_port.saturating_add(port_range.saturating_sub(1));
+    for port in first_port..=last_port {
+        if global_settings.allocated_ports.contains(&port) {
+            continue;
+        }
+        if TcpListener::bind((host.as_str(), port)).is_ok() {
+            global_settings.allocated_ports.insert(port);
+            global_settings.port_count = global_settings.allocated_ports.len() as u16;
+            return Ok(port);
+        }
+    }
+    Err(anyhow::anyhow!(
+        "No free port available for model server in range {}-{}",
+        first_port,
+        last_port
+    ))
+}
+
+/// Give port back to allocator after model server stops
+pub fn release_port_number(port: u16) {
+    let setting_ref = Arc::clone(GLOBAL_SETTINGS.get().unwrap());
+    let mut global_settings = setting_ref.lock().unwrap();
+    if global_settings.allocated_ports.remove(&port) {
+        global_settings.port_count = global_settings.allocated_ports.len() as u16;
+        tracing::info!("Released model server port {}", port);
+    }
 }
 
 pub fn initialize_synvek() {
@@ -80,6 +113,7 @@ pub fn get_global_settings() -> Settings {
     Settings {
         port: global_settings.port,
         port_count: global_settings.port_count,
+        allocated_ports: global_settings.allocated_ports.clone(),
     }
 }
 pub fn initialize_logging() {
@@ -12,6 +12,11 @@ pub enum MessageType {
     ProcessRunning,
     ProcessFailedToStart,
     ProcessFailedToTerminate,
+    ProcessStoppedGracefully,
+    ProcessKilledAfterTimeout,
+    ProcessRestarting,
+    ProcessUnloaded,
+    ProcessStateChanged,
     TaskAdded,
     TaskDeleted,
     TaskCompleted,
@@ -0,0 +1,365 @@
+use crate::common;
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Templates shipped with service, they can be attached to task without upload
+static BUNDLED_CHAT_TEMPLATES: [(&str, &str); 2] = [
+    ("chatml", include_str!("chat_templates/chatml.jinja")),
+    (
+        "chatml_tool_call",
+        include_str!("chat_templates/chatml_tool_call.jinja"),
+    ),
+];
+
+/// Chat template which can be attached to task
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatTemplateInfo {
+    pub template_name: String,
+
+    /// Whether template is shipped with service, bundled template can't be replaced or deleted
+    pub bundled: bool,
+}
+
+/// Chat template attached to task, it overrides template embedded in model
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TaskChatTemplate {
+    pub task_name: String,
+
+    pub template_name: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ChatTemplateConfigs {
+    pub task_chat_templates: Vec<TaskChatTemplate>,
+}
+
+static CHAT_TEMPLATES_CONFIG_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_chat_template_dir() -> PathBuf {
+    let config = Config::new();
+    let mut chat_template_dir = config.get_data_dir();
+    chat_template_dir.push(common::CHAT_TEMPLATE_DIR_NAME);
+    chat_template_dir
+}
+
+fn get_chat_template_file(template_name: &str) -> PathBuf {
+    let mut chat_template_file = get_chat_template_dir();
+    chat_template_file.push(format!("{}.jinja", template_name));
+    chat_template_file
+}
+
+fn get_bundled_chat_template(template_name: &str) -> Option<&'static str> {
+    BUNDLED_CHAT_TEMPLATES
+        .iter()
+        .find(|(name, _)| *name == template_name)
+        .map(|(_, content)| *content)
+}
+
+fn load_chat_templates_config() -> ChatTemplateConfigs {
+    let config = Config::new();
+    let mut chat_templates_config = config.get_config_dir();
+    chat_templates_config.push(common::CHAT_TEMPLATES_FILE);
+    let data_result = fs::read_to_string(chat_templates_config.clone());
+    if let Ok(data) = data_result {
+        let chat_templates_result = serde_json::from_str(&data);
+        if let Ok(chat_templates) = chat_templates_result {
+            return chat_templates;
+        } else {
+            tracing::error!(
+                "Invalid chat templates config file: {}",
+                chat_templates_config.display()
+            );
+        }
+    }
+    ChatTemplateConfigs::default()
+}
+
+fn write_chat_templates_config(chat_templates: &ChatTemplateConfigs) -> anyhow::Result<()> {
+    let config = Config::new();
+    let mut chat_templates_config = config.get_config_dir();
+    chat_templates_config.push(common::CHAT_TEMPLATES_FILE);
+    let json = serde_json::to_string_pretty(chat_templates)?;
+    fs::write(chat_templates_config, json)?;
+    Ok(())
+}
+
+/// Template name is used as file name, so only letters, digits, '-', '_' and '.' are allowed
+fn validate_template_name(template_name: &str) -> anyhow::Result<()> {
+    let valid = !template_name.is_empty()
+        && !template_name.starts_with('.')
+        && template_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if !valid {
+        return Err(anyhow::anyhow!("Invalid chat template name: {}", template_name));
+    }
+    Ok(())
+}
+
+/// Find end of tag which starts at offset, quoted strings in tag are skipped
+fn find_tag_end(content: &str, offset: usize, close: &str) -> Option<usize> {
+    let bytes = content.as_bytes();
+    let mut quote: Option<u8> = None;
+    let mut index = offset;
+    while index < bytes.len() {
+        let byte = bytes[index];
+        match quote {
+            // Escaped character in string is skipped
+            Some(_) if byte == b'\\' => index += 1,
+            Some(q) if byte == q => quote = None,
+            Some(_) => {}
+            None if byte == b'\'' || byte == b'"' => quote = Some(byte),
+            None if content[index..].starts_with(close) => return Some(index),
+            None => {}
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Check template syntax: tags are closed and block statements are nested properly. Template is
+/// rendered by backend, so expressions themselves are not evaluated here.
+pub fn validate_chat_template(content: &str) -> anyhow::Result<()> {
+    if content.trim().is_empty() {
+        return Err(anyhow::anyhow!("Chat template is empty"));
+    }
+    if content.len() > common::MAX_CHAT_TEMPLATE_SIZE {
+        return Err(anyhow::anyhow!(
+            "Chat template is larger than {} bytes",
+            common::MAX_CHAT_TEMPLATE_SIZE
+        ));
+    }
+    if !content.contains("messages") {
+        return Err(anyhow::anyhow!("Chat template doesn't use messages"));
+    }
+    let mut blocks: Vec<String> = vec![];
+    let mut offset = 0;
+    while let Some(start) = content[offset..].find('{').map(|index| index + offset) {
+        let close = match content[start + 1..].chars().next() {
+            Some('{') => "}}",
+            Some('%') => "%}",
+            Some('#') => "#}",
+            _ => {
+                offset = start + 1;
+                continue;
+            }
+        };
+        let line = content[..start].matches('\n').count() + 1;
+        let end = if close == "#}" {
+            content[start + 2..].find(close).map(|index| index + start + 2)
+        } else {
+            find_tag_end(content, start + 2, close)
+        };
+        let Some(end) = end else {
+            return Err(anyhow::anyhow!("Unclosed tag at line {}", line));
+        };
+        offset = end + 2;
+        if close != "%}" {
+            continue;
+        }
+        let statement = content[start + 2..end].trim_matches(|c: char| c == '-' || c == '+');
+        let statement = statement.trim();
+        let keyword = statement.split_whitespace().next().unwrap_or_default();
+        match keyword {
+            "if" | "for" | "macro" | "call" | "filter" | "block" => {
+                blocks.push(keyword.to_string())
+            }
+            // Set with value is single statement, set without value captures block
+            "set" if !statement.contains('=') => blocks.push(keyword.to_string()),
+            "raw" => {
+                let Some(raw_end) = content[offset..].find("endraw") else {
+                    return Err(anyhow::anyhow!("Unclosed raw block at line {}", line));
+                };
+                offset = find_tag_end(content, offset + raw_end, "%}")
+                    .map(|index| index + 2)
+                    .unwrap_or(content.len());
+            }
+            "elif" | "else" => {
+                let parent = blocks.last().map(|block| block.as_str());
+                let valid = parent == Some("if") || (keyword == "else" && parent == Some("for"));
+                if !valid {
+                    return Err(anyhow::anyhow!("Unexpected {} at line {}", keyword, line));
+                }
+            }
+            _ if keyword.starts_with("end") => {
+                let block = blocks.pop();
+                if block.as_deref() != Some(&keyword[3..]) {
+                    return Err(anyhow::anyhow!("Unexpected {} at line {}", keyword, line));
+                }
+            }
+            "" => return Err(anyhow::anyhow!("Empty statement at line {}", line)),
+            _ => {}
+        }
+    }
+    if let Some(block) = blocks.last() {
+        return Err(anyhow::anyhow!("Missing end{} in chat template", block));
+    }
+    Ok(())
+}
+
+/// Bundled templates followed by uploaded templates
+pub fn get_chat_templates() -> Vec<ChatTemplateInfo> {
+    let mut templates: Vec<ChatTemplateInfo> = BUNDLED_CHAT_TEMPLATES
+        .iter()
+        .map(|(name, _)| ChatTemplateInfo {
+            template_name: name.to_string(),
+            bundled: true,
+        })
+        .collect();
+    if let Ok(entries) = fs::read_dir(get_chat_template_dir()) {
+        let mut uploaded_templates: Vec<ChatTemplateInfo> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "jinja"))
+            .filter_map(|path| path.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+            .filter(|name| get_bundled_chat_template(name).is_none())
+            .map(|name| ChatTemplateInfo {
+                template_name: name,
+                bundled: false,
+            })
+            .collect();
+        uploaded_templates.sort_by(|a, b| a.template_name.cmp(&b.template_name));
+        templates.append(&mut uploaded_templates);
+    }
+    templates
+}
+
+/// Save uploaded template after it is validated, template with same name is replaced
+pub fn save_chat_template(template_name: &str, content: &str) -> anyhow::Result<()> {
+    validate_template_name(template_name)?;
+    if get_bundled_chat_template(template_name).is_some() {
+        return Err(anyhow::anyhow!(
+            "Bundled chat template can't be replaced: {}",
+            template_name
+        ));
+    }
+    validate_chat_template(content)?;
+    fs::create_dir_all(get_chat_template_dir())?;
+    fs::write(get_chat_template_file(template_name), content)?;
+    Ok(())
+}
+
+/// Delete uploaded template, template attached to task must be detached first
+pub fn delete_chat_template(template_name: &str) -> anyhow::Result<()> {
+    validate_template_name(template_name)?;
+    if get_bundled_chat_template(template_name).is_some() {
+        return Err(anyhow::anyhow!(
+            "Bundled chat template can't be deleted: {}",
+            template_name
+        ));
+    }
+    let _lock = CHAT_TEMPLATES_CONFIG_LOCK.lock().unwrap();
+    let task_chat_template = load_chat_templates_config()
+        .task_chat_templates
+        .into_iter()
+        .find(|element| element.template_name == template_name);
+    if let Some(task_chat_template) = task_chat_template {
+        return Err(anyhow::anyhow!(
+            "Chat template {} is used by model {}",
+            template_name,
+            task_chat_template.task_name
+        ));
+    }
+    let chat_template_file = get_chat_template_file(template_name);
+    if !chat_template_file.exists() {
+        return Err(anyhow::anyhow!("Chat template not found: {}", template_name));
+    }
+    fs::remove_file(chat_template_file)?;
+    Ok(())
+}
+
+pub fn get_task_chat_template(task_name: &str) -> Option<String> {
+    let _lock = CHAT_TEMPLATES_CONFIG_LOCK.lock().unwrap();
+    load_chat_templates_config()
+        .task_chat_templates
+        .into_iter()
+        .find(|element| element.task_name == task_name)
+        .map(|element| element.template_name)
+}
+
+/// Attach template to task, template is detached if template name is not provided
+pub fn set_task_chat_template(
+    task_name: &str,
+    template_name: Option<String>,
+) -> anyhow::Result<()> {
+    if let Some(template_name) = template_name.as_ref() {
+        validate_template_name(template_name)?;
+        if get_bundled_chat_template(template_name).is_none()
+            && !get_chat_template_file(template_name).exists()
+        {
+            return Err(anyhow::anyhow!("Chat template not found: {}", template_name));
+        }
+    }
+    let _lock = CHAT_TEMPLATES_CONFIG_LOCK.lock().unwrap();
+    let mut chat_templates_config = load_chat_templates_config();
+    chat_templates_config
+        .task_chat_templates
+        .retain(|element| element.task_name != task_name);
+    if let Some(template_name) = template_name {
+        chat_templates_config
+            .task_chat_templates
+            .push(TaskChatTemplate {
+                task_name: task_name.to_string(),
+                template_name,
+            });
+    }
+    write_chat_templates_config(&chat_templates_config)
+}
+
+/// File of template attached to task, bundled template is written to chat template dir so backend
+/// can load it from file.
+pub fn get_task_chat_template_path(task_name: &str) -> Option<PathBuf> {
+    let template_name = get_task_chat_template(task_name)?;
+    let chat_template_file = get_chat_template_file(template_name.as_str());
+    if let Some(content) = get_bundled_chat_template(template_name.as_str()) {
+        let result = fs::create_dir_all(get_chat_template_dir())
+            .and_then(|_| fs::write(chat_template_file.clone(), content));
+        if let Err(e) = result {
+            tracing::error!(
+                "Failed to write bundled chat template {} with error: {}",
+                template_name,
+                e
+            );
+            return None;
+        }
+    } else if !chat_template_file.exists() {
+        tracing::error!(
+            "Chat template {} of task {} is not found",
+            template_name,
+            task_name
+        );
+        return None;
+    }
+    Some(chat_template_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_bundled_chat_templates() {
+        BUNDLED_CHAT_TEMPLATES.iter().for_each(|(name, content)| {
+            assert!(
+                validate_chat_template(content).is_ok(),
+                "Invalid bundled chat template: {}",
+                name
+            );
+        });
+    }
+
+    #[test]
+    fn test_validate_invalid_chat_templates() {
+        assert!(validate_chat_template("{% for message in messages %}{{ message }}").is_err());
+        assert!(validate_chat_template("{% if messages %}{% endfor %}").is_err());
+        assert!(validate_chat_template("{{ messages[0]").is_err());
+        assert!(validate_chat_template("{% else %}{{ messages }}").is_err());
+        assert!(
+            validate_chat_template("{% for m in messages %}{{ '%}' }}{% else %}{% endfor %}")
+                .is_ok()
+        );
+    }
+}